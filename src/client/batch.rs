@@ -0,0 +1,184 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, MissedTickBehavior};
+
+use crate::client::source::fit_colors;
+use crate::{Color, Controller, OpenRgbError, OpenRgbResult};
+
+/// Pending colors of a `BatchWriter`.
+#[derive(Debug)]
+struct BatchState {
+    colors: Vec<Color>,
+    dirty: bool,
+}
+
+impl BatchState {
+    /// Returns the colors to upload if anything changed since the last upload, and marks them as uploaded.
+    fn take_dirty(&mut self) -> Option<Vec<Color>> {
+        std::mem::take(&mut self.dirty).then(|| self.colors.clone())
+    }
+}
+
+/// Coalesces LED updates for a controller, uploading them at most once per interval.
+///
+/// This is useful when colors are produced in bursts (e.g. reacting to audio),
+/// faster than the device can consume them.
+/// Every upload sends all LEDs of the controller in a single update.
+///
+/// The writer starts with the colors from the cached controller data.
+/// Dropping the writer stops it without uploading pending changes, use `stop()` to upload them.
+#[derive(Debug)]
+pub struct BatchWriter {
+    controller: Arc<Controller>,
+    state: Arc<Mutex<BatchState>>,
+    task: JoinHandle<()>,
+}
+
+impl BatchWriter {
+    /// Creates a batch writer for `controller`, uploading pending changes every `interval`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime, since the upload task is spawned on the current runtime.
+    pub fn new(controller: Controller, interval: Duration) -> Self {
+        let colors = fit_colors(&controller.data().colors, controller.num_leds());
+        let controller = Arc::new(controller);
+        let state = Arc::new(Mutex::new(BatchState {
+            colors,
+            dirty: false,
+        }));
+        let task = tokio::spawn(Self::run(
+            Arc::clone(&controller),
+            Arc::clone(&state),
+            interval,
+        ));
+        Self {
+            controller,
+            state,
+            task,
+        }
+    }
+
+    async fn run(controller: Arc<Controller>, state: Arc<Mutex<BatchState>>, interval: Duration) {
+        let mut ticks = tokio::time::interval_at(Instant::now() + interval, interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticks.tick().await;
+            let colors = state.lock().expect("Batch state poisoned").take_dirty();
+            if let Some(colors) = colors {
+                if let Err(e) = controller.set_leds(colors).await {
+                    tracing::warn!("Batched update of {} failed: {e}", controller.name());
+                }
+            }
+        }
+    }
+
+    /// Returns the controller this writer updates.
+    pub fn controller(&self) -> &Controller {
+        &self.controller
+    }
+
+    /// Sets the LED at `index` to `color`, it is uploaded with the next flush.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `index` is out of bounds.
+    pub fn set(&self, index: usize, color: Color) -> OpenRgbResult<()> {
+        let mut state = self.state.lock().expect("Batch state poisoned");
+        let len = state.colors.len();
+        let led = state
+            .colors
+            .get_mut(index)
+            .ok_or(OpenRgbError::CommandError(format!(
+                "LED index {index} out of range for controller {} with {len} LEDs",
+                self.controller.name()
+            )))?;
+        *led = color;
+        state.dirty = true;
+        Ok(())
+    }
+
+    /// Uploads pending changes immediately, instead of waiting for the next interval.
+    ///
+    /// Does nothing if there are no pending changes.
+    pub async fn flush_now(&self) -> OpenRgbResult<()> {
+        let colors = self
+            .state
+            .lock()
+            .expect("Batch state poisoned")
+            .take_dirty();
+        match colors {
+            Some(colors) => self.controller.set_leds(colors).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Stops this writer, uploads pending changes and returns the controller.
+    pub async fn stop(mut self) -> OpenRgbResult<Controller> {
+        self.task.abort();
+        // wait for an upload in progress to finish, so the controller is no longer shared
+        let _ = (&mut self.task).await;
+        self.flush_now().await?;
+        let controller = Arc::clone(&self.controller);
+        drop(self);
+        Ok(Arc::into_inner(controller).expect("Batch writer task should have stopped"))
+    }
+}
+
+impl Drop for BatchWriter {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::protocol::mock::{MockServer, controller_fixture};
+    use crate::{BatchWriter, Color, OpenRgbResult, PacketId};
+
+    #[tokio::test]
+    async fn test_coalesce_updates() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[10])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+        let writer = BatchWriter::new(controller, Duration::from_millis(100));
+
+        for i in 0..100 {
+            writer.set(i % 10, Color::new(i as u8, 0, 0))?;
+        }
+        assert!(writer.set(10, Color::default()).is_err());
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 1)
+            .await;
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(mock.packets_of(PacketId::RGBControllerUpdateLeds).len(), 1);
+
+        let mut msg = packets[0].message(5);
+        let _data_size = msg.read_u32()?;
+        let colors = msg.read_value::<Vec<Color>>()?;
+        assert_eq!(colors[0], Color::new(90, 0, 0));
+        assert_eq!(colors[9], Color::new(99, 0, 0));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_flush_now_and_stop() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+        let writer = BatchWriter::new(controller, Duration::from_secs(3600));
+
+        writer.flush_now().await?;
+        writer.set(0, Color::new(255, 0, 0))?;
+        writer.flush_now().await?;
+        writer.set(1, Color::new(0, 255, 0))?;
+        let controller = writer.stop().await?;
+
+        assert_eq!(controller.name(), "Strip");
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 2)
+            .await;
+        Ok(())
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::BTreeMap;
+
+use crate::client::controller::{ColorOrigin, LedTarget, LedWrite};
+use crate::client::source::fit_colors;
+use crate::{Color, Controller, OpenRgbError, OpenRgbResult};
+
+/// Buffers single LED updates and sends them as one packet when the scope ends.
+///
+/// Created by `Controller::begin_coalescing()`.
+/// When `end()` is called, or the guard is dropped, the buffered updates are sent through the controller,
+/// like `Controller::set_leds()` and friends, so mode checks, gamma and brightness apply to them too.
+/// The updates are sent as a single LED update if only one LED was touched, as an update of its zone
+/// if all touched LEDs are in the same zone, or as an update of the whole controller otherwise.
+/// LEDs in the update that were not touched keep their colors from the cached controller data.
+///
+/// Dropping the guard sends the updates in a background task, so errors can only be logged,
+/// and the update can arrive at the server after LED updates sent directly after the guard was dropped.
+/// Prefer calling `end()` to handle errors and keep updates in order.
+#[derive(Debug)]
+pub struct CoalescingGuard<'a> {
+    controller: &'a Controller,
+    pending: BTreeMap<usize, Color>,
+}
+
+impl<'a> CoalescingGuard<'a> {
+    pub(crate) fn new(controller: &'a Controller) -> Self {
+        Self {
+            controller,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Buffers setting the LED with the given `led` index to `color`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `led` is out of bounds for the controller.
+    pub fn set_led(&mut self, led: usize, color: Color) -> OpenRgbResult<()> {
+        if led >= self.controller.num_leds() {
+            return Err(OpenRgbError::CommandError(format!(
+                "LED index {led} out of range for controller {} with {} LEDs",
+                self.controller.name(),
+                self.controller.num_leds()
+            )));
+        }
+        self.pending.insert(led, color);
+        Ok(())
+    }
+
+    /// Returns the number of LEDs with a buffered update.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Sends the buffered updates, ending this scope.
+    pub async fn end(mut self) -> OpenRgbResult<()> {
+        match self.take_update()? {
+            Some(write) => write.send().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Combines and clears the buffered updates, and prepares them for sending through the controller.
+    fn take_update(&mut self) -> OpenRgbResult<Option<LedWrite>> {
+        let pending = std::mem::take(&mut self.pending);
+        let controller = self.controller;
+        let (Some((&first, _)), Some((&last, &color))) =
+            (pending.first_key_value(), pending.last_key_value())
+        else {
+            return Ok(None);
+        };
+        if pending.len() == 1 {
+            let write =
+                controller.prepare_write(LedTarget::Led(last), &[color], ColorOrigin::User)?;
+            return Ok(Some(write));
+        }
+        if pending.len() == controller.num_leds() {
+            let colors = pending.into_values().collect::<Vec<_>>();
+            let write =
+                controller.prepare_write(LedTarget::Controller, &colors, ColorOrigin::User)?;
+            return Ok(Some(write));
+        }
+
+        // untouched LEDs keep the colors of the device, only the touched ones are user colors
+        let mut colors = fit_colors(&controller.data().colors, controller.num_leds());
+        for (led, color) in pending {
+            colors[led] = controller.transform(color);
+        }
+        let zone = controller.get_all_zones().find(|z| {
+            let range = z.offset()..z.offset() + z.num_leds();
+            range.contains(&first) && range.contains(&last)
+        });
+        let write = match zone {
+            Some(zone) => {
+                let range = zone.offset()..zone.offset() + zone.num_leds();
+                let target = LedTarget::Zone(zone.zone_id());
+                controller.prepare_write(target, &colors[range], ColorOrigin::Device)?
+            }
+            None => {
+                controller.prepare_write(LedTarget::Controller, &colors, ColorOrigin::Device)?
+            }
+        };
+        Ok(Some(write))
+    }
+}
+
+impl Drop for CoalescingGuard<'_> {
+    fn drop(&mut self) {
+        let update = match self.take_update() {
+            Ok(Some(update)) => update,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::warn!(
+                    "Coalesced update of controller {} failed: {e}",
+                    self.controller.name()
+                );
+                return;
+            }
+        };
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::warn!(
+                "Coalesced updates for controller {} dropped outside of a tokio runtime",
+                self.controller.name()
+            );
+            return;
+        };
+        let name = self.controller.name().to_string();
+        runtime.spawn(async move {
+            if let Err(e) = update.send().await {
+                tracing::warn!("Coalesced update of controller {name} failed: {e}");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::protocol::mock::{MockServer, controller_fixture};
+    use crate::{Color, OpenRgbResult, PacketId};
+
+    #[tokio::test]
+    async fn test_adjacent_leds_single_packet() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2, 10])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        let mut guard = controller.begin_coalescing();
+        for i in 0..10 {
+            guard.set_led(2 + i, Color::new(i as u8, 0, 0))?;
+        }
+        assert!(guard.set_led(12, Color::default()).is_err());
+        guard.end().await?;
+
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateZoneLeds, 1)
+            .await;
+        let mut msg = packets[0].message(5);
+        let _data_size = msg.read_u32()?;
+        assert_eq!(msg.read_u32()?, 1);
+        let colors = msg.read_value::<Vec<Color>>()?;
+        assert_eq!(
+            colors,
+            (0..10).map(|i| Color::new(i, 0, 0)).collect::<Vec<_>>()
+        );
+        assert!(
+            mock.packets_of(PacketId::RGBControllerUpdateSingleLed)
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_flush_on_drop() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2, 2])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+        let red = Color::new(255, 0, 0);
+
+        {
+            let mut guard = controller.begin_coalescing();
+            guard.set_led(1, red)?;
+            guard.set_led(2, red)?;
+        }
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 1)
+            .await;
+        let mut msg = packets[0].message(5);
+        let _data_size = msg.read_u32()?;
+        let black = Color::default();
+        assert_eq!(
+            msg.read_value::<Vec<Color>>()?,
+            vec![black, red, red, black]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_partial_zone_single_packet() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[2, 10]);
+        let kept = Color::new(0, 0, 9);
+        data.colors = vec![kept; 12];
+        let mock = MockServer::start(5, vec![data]).await;
+        let mut controller = mock.client().await.get_controller(0).await?;
+        controller.set_brightness(0.5);
+        let red = Color::new(255, 0, 0);
+
+        let mut guard = controller.begin_coalescing();
+        for led in 3..7 {
+            guard.set_led(led, red)?;
+        }
+        guard.end().await?;
+
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateZoneLeds, 1)
+            .await;
+        let mut msg = packets[0].message(5);
+        let _data_size = msg.read_u32()?;
+        assert_eq!(msg.read_u32()?, 1);
+        // untouched LEDs keep the device colors, brightness only applies to the touched ones
+        let mut expected = vec![kept; 10];
+        expected[1..5].fill(Color::new(128, 0, 0));
+        assert_eq!(msg.read_value::<Vec<Color>>()?, expected);
+        assert!(
+            mock.packets_of(PacketId::RGBControllerUpdateSingleLed)
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_strict_mode_checked() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[3]);
+        data.active_mode = 1;
+        let mock = MockServer::start(5, vec![data]).await;
+        let mut controller = mock.client().await.get_controller(0).await?;
+        controller.set_strict_addressable_mode(true);
+
+        let mut guard = controller.begin_coalescing();
+        guard.set_led(0, Color::new(255, 0, 0))?;
+        let err = guard.end().await.unwrap_err();
+        assert!(err.to_string().contains("mode Static"), "{err}");
+        assert!(
+            mock.packets_of(PacketId::RGBControllerUpdateSingleLed)
+                .is_empty()
+        );
+        Ok(())
+    }
+}
@@ -118,6 +118,21 @@ impl<'a> UpdateLedCommandGroup<'a> {
         cmd.add_set_leds(colors)
     }
 
+    /// Add a command to update multiple LEDs in a controller, starting at LED index `offset`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the controller is not found in this group.
+    pub fn add_update_at(
+        &mut self,
+        controller_id: impl ControllerIndex,
+        offset: usize,
+        colors: Vec<Color>,
+    ) -> OpenRgbResult<()> {
+        let cmd = self.get_controller_mut(controller_id)?;
+        cmd.add_set_leds_at(offset, colors)
+    }
+
     /// Add a command to update a zone in a controller.
     ///
     /// # Errors
@@ -172,10 +187,13 @@ impl<'a> UpdateLedCommandGroup<'a> {
 /// meaning only a single update is actually sent to the controller.
 ///
 /// When two commands write to the same LED, the last command will overwrite the previous one.
+/// Use `track_conflicts()` to find out which LEDs were written more than once.
 #[derive(Debug)]
 pub struct UpdateLedCommand<'a> {
     controller: &'a Controller,
     colors: Vec<Color>,
+    /// Number of writes per LED, only kept when conflict tracking is enabled.
+    write_counts: Option<Vec<u32>>,
 }
 
 impl<'a> UpdateLedCommand<'a> {
@@ -183,9 +201,32 @@ impl<'a> UpdateLedCommand<'a> {
         Self {
             controller,
             colors: Vec::with_capacity(controller.num_leds()),
+            write_counts: None,
         }
     }
 
+    /// Enables tracking of LEDs that are written more than once, see `conflicts()`.
+    ///
+    /// This is off by default, as it adds overhead to every added command.
+    /// Only writes added after calling this are tracked.
+    pub fn track_conflicts(mut self) -> Self {
+        self.write_counts.get_or_insert_with(Vec::new);
+        self
+    }
+
+    /// Returns the indices of LEDs that were written more than once, in ascending order.
+    ///
+    /// Always returns an empty list if `track_conflicts()` was not enabled.
+    pub fn conflicts(&self) -> Vec<usize> {
+        self.write_counts
+            .iter()
+            .flatten()
+            .enumerate()
+            .filter(|(_, count)| **count > 1)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
     /// Executes this command, sending the update to the controller.
     pub async fn execute(self) -> OpenRgbResult<()> {
         self.controller.set_leds(self.colors).await?;
@@ -210,6 +251,40 @@ impl<'a> UpdateLedCommand<'a> {
         })
     }
 
+    /// Adds a command to update multiple LEDs in this controller, starting at LED index `offset`.
+    ///
+    /// This is for layouts that are precomputed in absolute LED indices, for example from a `FrameBuffer`.
+    /// Colors past the end of the controller are dropped with a warning.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error instead of dropping colors if strict addressable mode is enabled,
+    /// see `Controller::set_strict_addressable_mode()`.
+    pub fn add_set_leds_at(&mut self, offset: usize, colors: Vec<Color>) -> OpenRgbResult<()> {
+        let num_leds = self.controller.num_leds();
+        if offset.saturating_add(colors.len()) > num_leds {
+            if self.controller.strict_addressable_mode() {
+                return Err(OpenRgbError::CommandError(format!(
+                    "Controller {} was given {} colors at offset {offset}, while its length is {num_leds}",
+                    self.controller.name(),
+                    colors.len(),
+                )));
+            }
+            tracing::warn!(
+                "Controller {} was given {} colors at offset {}, while its length is {}. Colors past the end are dropped.",
+                self.controller.name(),
+                colors.len(),
+                offset,
+                num_leds
+            );
+        }
+        let len = colors.len().min(num_leds.saturating_sub(offset));
+        if len == 0 {
+            return Ok(());
+        }
+        self.set_colors(offset, &colors[..len])
+    }
+
     /// Adds a command to update a single LED in a zone in this controller.
     pub fn add_set_zone_led(
         &mut self,
@@ -296,7 +371,8 @@ impl<'a> UpdateLedCommand<'a> {
                     )
                 }
 
-                self.set_colors(0, &colors)?;
+                let len = colors.len().min(self.controller.num_leds());
+                self.set_colors(0, &colors[..len])?;
             }
             UpdateCommand::Zone {
                 controller_id: _,
@@ -366,6 +442,103 @@ impl<'a> UpdateLedCommand<'a> {
             self.colors.resize(len, Color::default());
         }
         self.colors[offset..len].copy_from_slice(colors);
+        if let Some(counts) = &mut self.write_counts {
+            if counts.len() < len {
+                counts.resize(len, 0);
+            }
+            counts[offset..len].iter_mut().for_each(|c| *c += 1);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::SegmentData;
+    use crate::protocol::mock::{MockServer, controller_fixture};
+    use crate::{Color, OpenRgbResult, PacketId};
+
+    #[tokio::test]
+    async fn test_conflicts() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[2, 6]);
+        data.zones[1].segments.replace(vec![
+            SegmentData::new("Left", 0, 3),
+            SegmentData::new("Right", 3, 3),
+        ]);
+        let mock = MockServer::start(5, vec![data]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+        let red = Color::new(255, 0, 0);
+
+        let mut cmd = controller.cmd().track_conflicts();
+        cmd.add_set_zone_leds(1, vec![red; 6])?;
+        cmd.add_set_segment_leds(1, 1, vec![red; 3])?;
+        cmd.add_set_led(0, red)?;
+        assert_eq!(cmd.conflicts(), vec![5, 6, 7]);
+
+        let mut cmd = controller.cmd();
+        cmd.add_set_zone_leds(1, vec![red; 6])?;
+        cmd.add_set_segment_leds(1, 1, vec![red; 3])?;
+        assert!(cmd.conflicts().is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_leds_at() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2, 3])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+        let red = Color::new(255, 0, 0);
+        let blue = Color::new(0, 0, 255);
+        let black = Color::default();
+
+        let mut cmd = controller.cmd();
+        cmd.add_set_leds_at(1, vec![red, red])?;
+        cmd.add_set_leds_at(4, vec![blue, blue])?;
+        cmd.add_set_leds_at(7, vec![blue])?;
+        cmd.add_set_leds_at(usize::MAX, vec![blue])?;
+        cmd.execute().await?;
+
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 1)
+            .await;
+        let mut msg = packets[0].message(5);
+        let _data_size = msg.read_u32()?;
+        assert_eq!(
+            msg.read_value::<Vec<Color>>()?,
+            vec![black, red, red, black, blue]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_leds_at_strict() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[3])]).await;
+        let mut controller = mock.client().await.get_controller(0).await?;
+        controller.set_strict_addressable_mode(true);
+        let red = Color::new(255, 0, 0);
+
+        let mut cmd = controller.cmd();
+        cmd.add_set_leds_at(1, vec![red, red])?;
+        assert!(cmd.add_set_leds_at(2, vec![red, red]).is_err());
+        assert!(cmd.add_set_leds_at(usize::MAX, vec![red]).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_over_length_controller_update() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[3])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+        let red = Color::new(255, 0, 0);
+
+        let mut cmd = controller.cmd();
+        cmd.add_set_leds(vec![red; 5])?;
+        cmd.execute().await?;
+
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 1)
+            .await;
+        let mut msg = packets[0].message(5);
+        let _data_size = msg.read_u32()?;
+        assert_eq!(msg.read_value::<Vec<Color>>()?, vec![red; 3]);
         Ok(())
     }
 }
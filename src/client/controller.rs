@@ -1,14 +1,25 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use flagset::FlagSet;
+use tokio::time::Instant;
+
 use crate::{
     OpenRgbError, OpenRgbResult,
     client::command::UpdateLedCommand,
-    data::{ModeData, ModeFlag},
+    data::{ControllerFlags, Direction, ModeData, ModeFlag},
     protocol::{
         OpenRgbProtocol,
         data::{Color, ControllerData},
     },
 };
 
-use super::Zone;
+use super::{
+    CoalescingGuard, ColorSource, Effect, EffectContext, FrameBuffer, Zone, ZoneMatrixPlacement,
+    source::fit_colors, traced,
+};
 
 /// An RGBController, which represents a single RGB device that can be controlled.
 ///
@@ -18,6 +29,134 @@ pub struct Controller {
     id: usize,
     proto: OpenRgbProtocol,
     data: ControllerData,
+    /// Whether `proto` is a connection only used by this controller.
+    isolated: bool,
+    /// Whether LED updates fail if the active mode has no per-LED colors.
+    strict_addressable_mode: bool,
+    /// Whether LED updates first switch to a controllable mode, see `auto_controllable()`.
+    auto_controllable: bool,
+    /// Whether a controllable mode was set since the last mode change.
+    controllable_mode_set: AtomicBool,
+    /// Gamma applied to colors before sending them, see `set_gamma()`.
+    gamma: f32,
+    /// Factor colors are scaled with before sending them, see `set_brightness()`.
+    brightness: f32,
+    /// Color buffers recently sent with `set_leds()`, see `with_history()`.
+    history: Mutex<ColorHistory>,
+}
+
+/// Number of mode changes the receiver of `Controller::watch_mode()` buffers.
+pub const WATCH_MODE_BUFFER: usize = 16;
+
+/// Ring buffer of the last `frames` color buffers sent to a controller.
+#[derive(Debug, Default)]
+struct ColorHistory {
+    frames: usize,
+    buffers: VecDeque<Vec<Color>>,
+}
+
+impl ColorHistory {
+    fn resize(&mut self, frames: usize) {
+        self.frames = frames;
+        while self.buffers.len() > frames {
+            self.buffers.pop_front();
+        }
+    }
+
+    fn push(&mut self, colors: &[Color]) {
+        if self.frames == 0 {
+            return;
+        }
+        if self.buffers.len() == self.frames {
+            self.buffers.pop_front();
+        }
+        self.buffers.push_back(colors.to_vec());
+    }
+}
+
+/// The LEDs written by a single LED update.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LedTarget {
+    /// A single LED, by its index in the controller's LED array.
+    Led(usize),
+    /// All LEDs of the zone with this id.
+    Zone(usize),
+    /// All LEDs of the controller.
+    Controller,
+}
+
+/// Where the colors of an LED update come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColorOrigin {
+    /// Colors given by the user, gamma and brightness are applied before sending them.
+    User,
+    /// Colors as reported by the device, e.g. from `data().colors`.
+    /// These already had gamma and brightness applied when they were set, so they are sent unchanged.
+    Device,
+}
+
+/// An LED update that passed the checks of its controller, ready to be sent.
+///
+/// Created by `Controller::prepare_write()`. This owns everything it needs,
+/// so it can be sent from a task that does not borrow the controller.
+pub(crate) struct LedWrite {
+    proto: OpenRgbProtocol,
+    controller_id: u32,
+    /// Controllable mode to switch to first, see `Controller::auto_controllable()`.
+    mode: Option<ModeData>,
+    target: LedTarget,
+    colors: Vec<Color>,
+}
+
+impl LedWrite {
+    /// Switches to the controllable mode of this write, if it has one.
+    ///
+    /// Use this to switch the mode once before sending several writes concurrently.
+    pub(crate) async fn switch_mode(&mut self) -> OpenRgbResult<()> {
+        match self.mode.take() {
+            Some(mode) => write_controllable_mode(&self.proto, self.controller_id, &mode).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Sends this update, switching to a controllable mode first if needed.
+    pub(crate) async fn send(mut self) -> OpenRgbResult<()> {
+        self.switch_mode().await?;
+        let id = self.controller_id;
+        match self.target {
+            LedTarget::Led(led) => {
+                let color = self.colors.first().copied().unwrap_or_default();
+                self.proto.update_led(id, led as i32, &color).await
+            }
+            LedTarget::Zone(zone_id) => {
+                self.proto
+                    .update_zone_leds(id, zone_id as u32, &self.colors)
+                    .await
+            }
+            LedTarget::Controller => self.proto.update_leds(id, &self.colors).await,
+        }
+    }
+}
+
+/// Sets and saves `mode`, which should be a controllable mode of the controller.
+async fn write_controllable_mode(
+    proto: &OpenRgbProtocol,
+    controller_id: u32,
+    mode: &ModeData,
+) -> OpenRgbResult<()> {
+    // just do both I guess
+    proto.update_mode(controller_id, mode).await?;
+    match proto.save_mode(controller_id, mode).await {
+        // saving modes requires protocol version 3, the mode is still applied on older servers
+        Err(OpenRgbError::UnsupportedOperation { .. }) => {
+            tracing::debug!(
+                "Server does not support saving modes, {} mode was not saved",
+                mode.name
+            );
+            Ok(())
+        }
+        res => res,
+    }
 }
 
 impl std::fmt::Debug for Controller {
@@ -25,15 +164,263 @@ impl std::fmt::Debug for Controller {
         f.debug_struct("Controller")
             .field("id", &self.id)
             .field("name", &self.data.name)
-            .field("num_leds", &self.data.num_leds)
+            .field("num_leds", &self.data.num_leds())
             .field("modes", &self.data.modes.len())
             .finish()
     }
 }
 
+fn non_empty(s: &str) -> Option<&str> {
+    (!s.is_empty()).then_some(s)
+}
+
+/// Linearly interpolates from `a` to `b`, at `step / steps` of the way.
+fn lerp_color(a: Color, b: Color, step: u32, steps: u32) -> Color {
+    let lerp =
+        |a: u8, b: u8| ((a as u32 * (steps - step) + b as u32 * step + steps / 2) / steps) as u8;
+    Color::new(lerp(a.r, b.r), lerp(a.g, b.g), lerp(a.b, b.b))
+}
+
 impl Controller {
     pub(crate) fn new(id: usize, proto: OpenRgbProtocol, data: ControllerData) -> Self {
-        Self { id, proto, data }
+        Self {
+            id,
+            proto,
+            data,
+            isolated: false,
+            strict_addressable_mode: false,
+            auto_controllable: false,
+            controllable_mode_set: AtomicBool::new(false),
+            gamma: 1.0,
+            brightness: 1.0,
+            history: Mutex::default(),
+        }
+    }
+
+    /// Returns a new handle to this controller that uses its own connection to the OpenRGB server.
+    ///
+    /// Requests of the returned controller do not have to wait for requests of other controllers,
+    /// which allows for concurrent updates such as `set_zones_concurrent()`.
+    /// The controller data is fetched again over the new connection.
+    pub async fn connect_isolated(&self) -> OpenRgbResult<Controller> {
+        let proto = self.proto.connect_clone().await?;
+        let data = proto.get_controller(self.id as u32).await?;
+        Ok(Self {
+            id: self.id,
+            proto,
+            data,
+            isolated: true,
+            strict_addressable_mode: self.strict_addressable_mode,
+            auto_controllable: self.auto_controllable,
+            controllable_mode_set: AtomicBool::new(
+                self.controllable_mode_set.load(Ordering::Relaxed),
+            ),
+            gamma: self.gamma,
+            brightness: self.brightness,
+            history: Mutex::default(),
+        })
+    }
+
+    /// Returns true if this controller has its own connection, see `connect_isolated()`.
+    pub fn is_isolated(&self) -> bool {
+        self.isolated
+    }
+
+    /// Returns `controller` with the connection and settings of `self`, for refreshing stale controllers.
+    pub(crate) fn reuse_connection(self, controller: Controller) -> Controller {
+        Self {
+            id: controller.id,
+            data: controller.data,
+            ..self
+        }
+    }
+
+    /// Returns true if `other` most likely refers to the same physical device, even if its id changed.
+    pub(crate) fn same_device(&self, other: &Controller) -> bool {
+        self.data.device_type == other.data.device_type
+            && self.data.name == other.data.name
+            && self.data.location == other.data.location
+            && self.data.serial == other.data.serial
+    }
+
+    /// Sets whether LED updates should fail when the active mode does not support per-LED colors.
+    ///
+    /// Devices silently ignore LED updates in modes like "Rainbow", so by default updates are sent anyway.
+    /// When enabled, `set_led()` and `set_leds()` return an error instead.
+    /// The check uses the cached active mode, so call `sync_controller_data()` after changing the mode.
+    pub fn set_strict_addressable_mode(&mut self, strict: bool) {
+        self.strict_addressable_mode = strict;
+    }
+
+    /// Returns true if LED updates fail when the active mode does not support per-LED colors.
+    ///
+    /// See `set_strict_addressable_mode()`.
+    pub fn strict_addressable_mode(&self) -> bool {
+        self.strict_addressable_mode
+    }
+
+    /// Sets whether LED updates should first switch this controller to a controllable mode.
+    ///
+    /// When enabled, the first LED update calls `set_controllable_mode()`.
+    /// This is only done again after the mode is changed through this controller, e.g. with `set_mode_by_name()`.
+    /// Mode changes made by other clients are not noticed.
+    pub fn auto_controllable(&mut self, enabled: bool) {
+        self.auto_controllable = enabled;
+    }
+
+    /// Checks an LED update of `colors` to `target` and prepares it for sending.
+    ///
+    /// Every LED update of a controller goes through here, so that all of them:
+    /// - switch to a controllable mode first if `auto_controllable()` is enabled and no controllable mode was set yet,
+    ///   or check the active mode if strict addressable mode is enabled,
+    /// - record user colors for the whole controller in the history, before applying gamma and brightness,
+    /// - apply gamma and brightness to user colors.
+    pub(crate) fn prepare_write(
+        &self,
+        target: LedTarget,
+        colors: &[Color],
+        origin: ColorOrigin,
+    ) -> OpenRgbResult<LedWrite> {
+        let mode = if self.auto_controllable {
+            if self.controllable_mode_set.swap(true, Ordering::Relaxed) {
+                None
+            } else {
+                let mode = self.controllable_mode();
+                if mode.is_err() {
+                    self.mode_changed();
+                }
+                Some(mode?)
+            }
+        } else {
+            self.check_addressable_mode()?;
+            None
+        };
+        if origin == ColorOrigin::User && target == LedTarget::Controller {
+            self.history
+                .lock()
+                .expect("Controller history poisoned")
+                .push(colors);
+        }
+        let colors = match origin {
+            ColorOrigin::User => colors.iter().map(|c| self.transform(*c)).collect(),
+            ColorOrigin::Device => colors.to_vec(),
+        };
+        Ok(LedWrite {
+            proto: self.proto.clone(),
+            controller_id: self.id as u32,
+            mode,
+            target,
+            colors,
+        })
+    }
+
+    /// Sends an LED update of `colors` to `target`, see `prepare_write()`.
+    pub(crate) async fn send_colors(
+        &self,
+        target: LedTarget,
+        colors: &[Color],
+        origin: ColorOrigin,
+    ) -> OpenRgbResult<()> {
+        self.prepare_write(target, colors, origin)?.send().await
+    }
+
+    /// Returns true if `set_controllable_mode()` was called since the mode last changed.
+    pub(crate) fn controllable_mode_active(&self) -> bool {
+        self.controllable_mode_set.load(Ordering::Relaxed)
+    }
+
+    /// Marks that the mode was changed, so that a controllable mode has to be set again.
+    pub(crate) fn mode_changed(&self) {
+        self.controllable_mode_set.store(false, Ordering::Relaxed);
+    }
+
+    /// Sets the gamma applied to colors sent with `set_led()`, `set_leds()` and `set_zone_leds()`.
+    ///
+    /// Every channel is sent as `255 * (value / 255) ^ gamma`, so values above 1.0 darken mid tones.
+    /// The default is 1.0, which leaves colors unchanged. Values that are not positive are ignored.
+    pub fn set_gamma(&mut self, gamma: f32) {
+        if !(gamma > 0.0 && gamma.is_finite()) {
+            tracing::warn!(
+                "Ignoring invalid gamma {gamma} for controller {}",
+                self.name()
+            );
+            return;
+        }
+        self.gamma = gamma;
+    }
+
+    /// Returns the gamma applied to colors before sending them, see `set_gamma()`.
+    pub fn gamma(&self) -> f32 {
+        self.gamma
+    }
+
+    /// Sets the factor every channel is multiplied with before sending colors, after applying gamma.
+    ///
+    /// The default is 1.0, 0.5 halves all channels. Channels are clamped to 255, negative values are ignored.
+    /// Like `set_gamma()`, this applies to `set_led()`, `set_leds()` and `set_zone_leds()`.
+    pub fn set_brightness(&mut self, factor: f32) {
+        if !(factor >= 0.0 && factor.is_finite()) {
+            tracing::warn!(
+                "Ignoring invalid brightness {factor} for controller {}",
+                self.name()
+            );
+            return;
+        }
+        self.brightness = factor;
+    }
+
+    /// Returns the factor colors are scaled with before sending them, see `set_brightness()`.
+    pub fn brightness(&self) -> f32 {
+        self.brightness
+    }
+
+    /// Applies gamma and brightness to `color`.
+    pub(crate) fn transform(&self, color: Color) -> Color {
+        if self.gamma == 1.0 && self.brightness == 1.0 {
+            return color;
+        }
+        let channel = |c: u8| {
+            let c = (c as f32 / 255.0).powf(self.gamma) * self.brightness * 255.0;
+            c.round().min(255.0) as u8
+        };
+        Color::new(channel(color.r), channel(color.g), channel(color.b))
+    }
+
+    /// Keeps the last `frames` color buffers sent with `set_leds()`, for effects that fade out old frames.
+    ///
+    /// History is disabled by default, passing 0 disables it again.
+    /// Shrinking the history drops the oldest buffers.
+    pub fn with_history(&mut self, frames: usize) {
+        self.history_mut().resize(frames);
+    }
+
+    /// Calls `f` with the color buffers recently sent with `set_leds()`, oldest first, and returns its result.
+    ///
+    /// The buffers are empty if history is not enabled, see `with_history()`.
+    /// They are borrowed, not copied, and LED updates of this controller wait until `f` returns.
+    pub fn with_history_frames<R>(&self, f: impl FnOnce(&[Vec<Color>]) -> R) -> R {
+        let mut history = self.history.lock().expect("Controller history poisoned");
+        f(history.buffers.make_contiguous())
+    }
+
+    fn history_mut(&mut self) -> &mut ColorHistory {
+        self.history.get_mut().expect("Controller history poisoned")
+    }
+
+    /// Returns an error if strict addressable mode is enabled and the active mode has no per-LED colors.
+    fn check_addressable_mode(&self) -> OpenRgbResult<()> {
+        if !self.strict_addressable_mode {
+            return Ok(());
+        }
+        let mode = self.active_mode()?;
+        if !mode.flags.contains(ModeFlag::HasPerLEDColor) {
+            return Err(OpenRgbError::CommandError(format!(
+                "Controller {} is in mode {}, which does not support per-LED colors",
+                self.name(),
+                mode.name
+            )));
+        }
+        Ok(())
     }
 
     pub(crate) fn proto(&self) -> &OpenRgbProtocol {
@@ -55,9 +442,213 @@ impl Controller {
         &self.data
     }
 
+    /// Returns the flags of this controller.
+    ///
+    /// Controller flags are only sent since protocol version 5, on older versions this is empty.
+    pub fn flags(&self) -> FlagSet<ControllerFlags> {
+        self.data.flags.value().copied().unwrap_or_default()
+    }
+
+    /// Returns true if this controller reports any alternative LED names.
+    ///
+    /// This is false both on protocol versions < 5, which do not support them, and for controllers that report none.
+    /// Use `data().led_alt_names.is_supported()` to tell these apart.
+    pub fn has_alt_names(&self) -> bool {
+        self.data.led_alt_names.is_supported()
+            && self
+                .data
+                .led_alt_names
+                .value()
+                .is_some_and(|names| names.iter().any(|n| !n.is_empty()))
+    }
+
+    /// Returns true if this controller reports any flags.
+    ///
+    /// Like `has_alt_names()`, this is false both on protocol versions < 5 and for controllers without flags.
+    pub fn has_flags(&self) -> bool {
+        self.data.flags.is_supported() && !self.flags().is_empty()
+    }
+
+    /// Returns true if this controller is local to the OpenRGB server it is reported by.
+    pub fn is_local(&self) -> bool {
+        self.flags().contains(ControllerFlags::IsLocal)
+    }
+
+    /// Returns true if this controller is on a remote OpenRGB instance, connected to the server as a client.
+    pub fn is_remote(&self) -> bool {
+        self.flags().contains(ControllerFlags::IsRemote)
+    }
+
+    /// Returns true if this controller is a virtual device.
+    pub fn is_virtual(&self) -> bool {
+        self.flags().contains(ControllerFlags::IsVirtual)
+    }
+
+    /// Returns the vendor of this controller, or `None` if the device did not report one.
+    pub fn vendor(&self) -> Option<&str> {
+        non_empty(&self.data.vendor)
+    }
+
+    /// Returns the version of this controller, or `None` if the device did not report one.
+    pub fn version(&self) -> Option<&str> {
+        non_empty(&self.data.version)
+    }
+
+    /// Returns the serial number of this controller, or `None` if the device did not report one.
+    ///
+    /// Many devices leave this empty, so it cannot be relied on to identify a device.
+    pub fn serial(&self) -> Option<&str> {
+        non_empty(&self.data.serial)
+    }
+
+    /// Returns the location of this controller (e.g. a USB or I2C path), or `None` if the device did not report one.
+    pub fn location(&self) -> Option<&str> {
+        non_empty(&self.data.location)
+    }
+
     /// Returns the number of LEDs in this controller.
     pub fn num_leds(&self) -> usize {
-        self.data.num_leds
+        self.data.num_leds()
+    }
+
+    /// Returns a black color buffer with one color per LED of this controller.
+    ///
+    /// Use this as a blank canvas for `set_leds()`, see also `FrameBuffer::blank()`.
+    pub fn blank_buffer(&self) -> Vec<Color> {
+        vec![Color::default(); self.num_leds()]
+    }
+
+    /// Returns the number of LEDs declared by the zones of this controller.
+    ///
+    /// This is the same as `num_leds()`, and is the number of colors expected when setting all LEDs.
+    pub fn declared_led_count(&self) -> usize {
+        self.data.num_leds()
+    }
+
+    /// Returns the number of `Led` structs (names and values) reported by this controller.
+    ///
+    /// This should equal `declared_led_count()`, see `validate_led_counts()`.
+    pub fn led_struct_count(&self) -> usize {
+        self.data.leds.len()
+    }
+
+    /// Returns the number of colors in the color buffer reported by this controller.
+    ///
+    /// If this is lower than `declared_led_count()`, the device under-reports its LEDs,
+    /// which usually means some LEDs will not light up.
+    pub fn color_buffer_len(&self) -> usize {
+        self.data.colors.len()
+    }
+
+    /// Returns the hardware-specific value of the LED at `index`, see `Led::value()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `index` is out of bounds.
+    pub fn led_value(&self, index: usize) -> OpenRgbResult<u32> {
+        self.data
+            .leds
+            .get(index)
+            .map(|led| led.value())
+            .ok_or(OpenRgbError::CommandError(format!(
+                "LED {index} not found in controller {}",
+                self.name()
+            )))
+    }
+
+    /// Syncs the controller data and returns the current color of the LED at `index`.
+    ///
+    /// This fetches the full controller data from the server, so avoid calling it for many LEDs in a row.
+    /// Use `sync_controller_data()` and `data().colors` instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `index` is out of bounds or the controller did not report its colors.
+    pub async fn led_color(&mut self, index: usize) -> OpenRgbResult<Color> {
+        if index >= self.num_leds() {
+            return Err(OpenRgbError::CommandError(format!(
+                "LED {index} not found in controller {} with {} LEDs",
+                self.name(),
+                self.num_leds()
+            )));
+        }
+        self.sync_controller_data().await?;
+        self.data
+            .colors
+            .get(index)
+            .copied()
+            .ok_or(OpenRgbError::ProtocolError(format!(
+                "Controller {} reported {} colors, expected {}",
+                self.name(),
+                self.data.colors.len(),
+                self.num_leds()
+            )))
+    }
+
+    /// Checks that the LED counts reported by this controller are consistent.
+    ///
+    /// Zones are laid out back to back in the controller's LED array, so the LED counts of all zones
+    /// should add up to the number of colors and the number of `Led`s the controller reported,
+    /// and the matrix map of a zone should only refer to LEDs of that zone.
+    /// Buggy device plugins can violate this, so it can be useful to run this once after fetching a controller.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ProtocolError` describing the first inconsistency found.
+    pub fn validate_led_counts(&self) -> OpenRgbResult<()> {
+        let zone_leds = self.num_leds();
+        if self.data.colors.len() != zone_leds {
+            return Err(OpenRgbError::ProtocolError(format!(
+                "Controller {} reported {} colors, but its zones have {zone_leds} LEDs",
+                self.name(),
+                self.data.colors.len()
+            )));
+        }
+        if self.data.leds.len() != zone_leds {
+            return Err(OpenRgbError::ProtocolError(format!(
+                "Controller {} reported {} LEDs, but its zones have {zone_leds} LEDs",
+                self.name(),
+                self.data.leds.len()
+            )));
+        }
+        let mut offset = 0;
+        for zone in &self.data.zones {
+            let len = zone.leds_count as usize;
+            let outside = zone.matrix.as_ref().and_then(|m| {
+                m.elements_row_major_iter()
+                    .find(|&&led| led != u32::MAX && led as usize >= len)
+            });
+            if let Some(led) = outside {
+                return Err(OpenRgbError::ProtocolError(format!(
+                    "Matrix of zone {} of controller {} refers to LED {led}, but the zone has LEDs {offset}..{} ({len} LEDs)",
+                    zone.name,
+                    self.name(),
+                    offset + len
+                )));
+            }
+            offset += len;
+        }
+        Ok(())
+    }
+
+    /// Returns true if the negotiated protocol version supports segments, which requires version 4.
+    ///
+    /// Use `Zone::has_segments()` to check whether a zone actually has segments.
+    pub fn supports_segments(&self) -> bool {
+        self.proto.get_protocol_version() >= 4
+    }
+
+    /// Returns true if the LEDs of this controller can be set individually.
+    ///
+    /// This requires the controller to have at least one LED and a mode with `ModeFlag::HasPerLEDColor`.
+    /// Some devices reported by OpenRGB, such as certain motherboards, do not meet this.
+    pub fn is_addressable(&self) -> bool {
+        self.num_leds() > 0
+            && self
+                .data
+                .modes
+                .iter()
+                .any(|m| m.flags.contains(ModeFlag::HasPerLEDColor))
     }
 
     /// Initialises a controller by setting it to a controllable mode.
@@ -80,18 +671,29 @@ impl Controller {
         Ok(())
     }
 
+    /// Returns the mode of this controller that is currently active.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the controller reported a negative or out of range active mode.
+    pub fn active_mode(&self) -> OpenRgbResult<&ModeData> {
+        self.data
+            .active_mode()
+            .ok_or(OpenRgbError::ProtocolError(format!(
+                "Controller {} reported invalid active mode {} ({} modes)",
+                self.name(),
+                self.data.active_mode,
+                self.data.modes.len()
+            )))
+    }
+
     /// Saves the current mode of this controller to the flash memory of the controller.
     ///
     /// # Important
     ///
     /// Using this frequently can cause wear on the flash memory, use this sparingly.
     pub async fn save_mode(&self) -> OpenRgbResult<()> {
-        let Some(active_mode) = self.data.active_mode() else {
-            return Err(OpenRgbError::CommandError(format!(
-                "Controller {} has no active mode",
-                self.name()
-            )));
-        };
+        let active_mode = self.active_mode()?;
         if !active_mode.flags.contains(ModeFlag::ManualSave) {
             return Err(OpenRgbError::CommandError(format!(
                 "Controller {} mode {} cannot be saved",
@@ -104,6 +706,14 @@ impl Controller {
 
     /// Sets this controller to a controllable mode.
     pub async fn set_controllable_mode(&self) -> OpenRgbResult<()> {
+        let mode = self.controllable_mode()?;
+        write_controllable_mode(&self.proto, self.id as u32, &mode).await?;
+        self.controllable_mode_set.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Returns the mode `set_controllable_mode()` switches to.
+    fn controllable_mode(&self) -> OpenRgbResult<ModeData> {
         // order: "direct", "custom", "static"
         let mut mode = self
             .get_mode_if_contains("direct")
@@ -121,10 +731,170 @@ impl Controller {
             mode.brightness_min.replace(100);
             mode.brightness_max.replace(100);
         }
+        Ok(mode)
+    }
+
+    /// Sets this controller to the mode with the given `name`, ignoring case.
+    ///
+    /// The mode is applied with its current settings, use `sync_controller_data()` to see the new active mode.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the controller has no mode with this name.
+    pub async fn set_mode_by_name(&self, name: &str) -> OpenRgbResult<()> {
+        let span = tracing::info_span!(
+            "set_mode_by_name",
+            controller_id = self.id,
+            controller = self.name()
+        );
+        traced(span, async {
+            let mode = self.get_mode_by_name(name)?;
+            self.mode_changed();
+            self.proto.update_mode(self.id as u32, mode).await
+        })
+        .await
+    }
+
+    /// Sets this controller to the mode with the given `name`, ignoring case, using `colors` as the mode colors.
+    ///
+    /// This is how to e.g. set the "Static" mode to a single color.
+    /// Use `sync_controller_data()` to see the new active mode.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the controller has no mode with this name,
+    /// or the number of colors is not between the `colors_min` and `colors_max` of the mode.
+    pub async fn set_mode_with_colors(&self, name: &str, colors: Vec<Color>) -> OpenRgbResult<()> {
+        let mut mode = self.get_mode_by_name(name)?.clone();
+        mode.colors = colors;
+        self.mode_changed();
+        self.proto.update_mode(self.id as u32, &mode).await
+    }
+
+    /// Sets this controller to the mode with the given device specific `value`, see `ModeData::value`.
+    ///
+    /// This is useful for configurations that store the hardware mode value instead of the mode index or name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the controller has no mode with this value.
+    pub async fn set_mode_by_value(&self, value: i32) -> OpenRgbResult<()> {
+        let mode =
+            self.data
+                .modes
+                .iter()
+                .find(|m| m.value == value)
+                .ok_or(OpenRgbError::CommandError(format!(
+                    "Controller {} has no mode with value {value}",
+                    self.name()
+                )))?;
+        self.mode_changed();
+        self.proto.update_mode(self.id as u32, mode).await
+    }
+
+    /// Returns the mode with the given `name`, ignoring case.
+    fn get_mode_by_name(&self, name: &str) -> OpenRgbResult<&ModeData> {
+        self.data
+            .modes
+            .iter()
+            .find(|m| m.name.eq_ignore_ascii_case(name))
+            .ok_or(OpenRgbError::CommandError(format!(
+                "Controller {} has no mode named {name}",
+                self.name()
+            )))
+    }
+
+    /// Sets the speed of the active mode and syncs the controller data.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the active mode does not have `ModeFlag::HasSpeed`.
+    pub async fn set_active_mode_speed(&mut self, speed: u32) -> OpenRgbResult<()> {
+        self.update_active_mode(ModeFlag::HasSpeed, "speed", |m| m.set_speed(speed))
+            .await
+    }
+
+    /// Sets the brightness of the active mode and syncs the controller data.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the active mode does not have `ModeFlag::HasBrightness`.
+    pub async fn set_active_mode_brightness(&mut self, brightness: u32) -> OpenRgbResult<()> {
+        self.update_active_mode(ModeFlag::HasBrightness, "brightness", |m| {
+            m.set_brightness(brightness)
+        })
+        .await
+    }
+
+    /// Sets the direction of the active mode and syncs the controller data.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the active mode does not support `direction`, see `Direction::required_flag()`.
+    pub async fn set_active_mode_direction(&mut self, direction: Direction) -> OpenRgbResult<()> {
+        self.update_active_mode(direction.required_flag(), "direction", |m| {
+            m.set_direction(direction)
+        })
+        .await
+    }
 
-        // just do both I guess
+    /// Applies `f` to a copy of the active mode, sends it to the controller and syncs the controller data.
+    async fn update_active_mode(
+        &mut self,
+        flag: ModeFlag,
+        setting: &str,
+        f: impl FnOnce(&mut ModeData),
+    ) -> OpenRgbResult<()> {
+        let active_mode = self.active_mode()?;
+        if !active_mode.flags.contains(flag) {
+            return Err(OpenRgbError::CommandError(format!(
+                "Controller {} mode {} does not support setting {setting}",
+                self.name(),
+                active_mode.name
+            )));
+        }
+        let mut mode = active_mode.clone();
+        f(&mut mode);
+        self.mode_changed();
         self.proto.update_mode(self.id as u32, &mode).await?;
-        self.proto.save_mode(self.id as u32, &mode).await
+        self.sync_controller_data().await
+    }
+
+    /// Switches this controller to the next mode, wrapping around after the last one.
+    ///
+    /// The controller data is synced afterwards, returns the name of the new active mode.
+    pub async fn next_mode(&mut self) -> OpenRgbResult<&str> {
+        self.cycle_mode(1).await
+    }
+
+    /// Switches this controller to the previous mode, wrapping around before the first one.
+    ///
+    /// The controller data is synced afterwards, returns the name of the new active mode.
+    pub async fn prev_mode(&mut self) -> OpenRgbResult<&str> {
+        self.cycle_mode(-1).await
+    }
+
+    async fn cycle_mode(&mut self, step: i32) -> OpenRgbResult<&str> {
+        let num_modes = self.data.modes.len() as i32;
+        if num_modes == 0 {
+            return Err(OpenRgbError::CommandError(format!(
+                "Controller {} has no modes",
+                self.name()
+            )));
+        }
+        let index = (self.data.active_mode + step).rem_euclid(num_modes) as usize;
+        self.mode_changed();
+        self.proto
+            .update_mode(self.id as u32, &self.data.modes[index])
+            .await?;
+        self.sync_controller_data().await?;
+        self.data
+            .active_mode()
+            .map(|m| m.name.as_str())
+            .ok_or(OpenRgbError::ProtocolError(format!(
+                "Controller {} has no active mode after switching modes",
+                self.data.name
+            )))
     }
 
     fn get_mode_if_contains(&self, pat: &str) -> Option<&ModeData> {
@@ -154,28 +924,400 @@ impl Controller {
             .map(|z| Zone::new(self, z.id as usize))
     }
 
-    /// Sets a single LED to the given `color`.
+    /// Returns the ids of the zones of this controller, sorted by LED count from large to small.
     ///
-    /// When doing many writes in rapid succession, it is recommended to use the `cmd()` method instead.
-    pub async fn set_led(&self, led: usize, color: Color) -> OpenRgbResult<()> {
-        self.proto
-            .update_led(self.id as u32, led as i32, &color)
-            .await
+    /// Zones with the same LED count keep their zone order.
+    pub fn zones_by_size(&self) -> Vec<usize> {
+        let mut zones = self.data.zones.iter().collect::<Vec<_>>();
+        zones.sort_by_key(|z| std::cmp::Reverse(z.leds_count));
+        zones.into_iter().map(|z| z.id as usize).collect()
     }
 
-    /// Sets all LEDs of this controller to a given `color`.
-    pub async fn set_all_leds(&self, color: Color) -> OpenRgbResult<()> {
-        let colors = vec![color; self.num_leds()];
-        self.set_leds(colors).await?;
-        Ok(())
+    /// Returns the id of the zone with the most LEDs, or `None` if this controller has no zones.
+    ///
+    /// If multiple zones have the most LEDs, the first of them is returned.
+    pub fn largest_zone(&self) -> Option<usize> {
+        self.zones_by_size().first().copied()
+    }
+
+    /// Returns a suggested placement for each matrix zone of this controller in a single combined grid.
+    ///
+    /// Every zone has its own coordinate space, so the zones are stacked vertically in zone order,
+    /// each starting at column 0 below the previous one, so that no two zones overlap.
+    /// This placement is a heuristic, as the protocol does not tell where zones are physically located.
+    /// Zones that are not a matrix are skipped.
+    pub fn matrix_layout(&self) -> Vec<ZoneMatrixPlacement> {
+        let mut row_offset = 0;
+        self.get_all_zones()
+            .filter_map(|zone| {
+                let placement = zone.matrix_placement(row_offset, 0).ok()?;
+                row_offset += placement.height();
+                Some(placement)
+            })
+            .collect()
+    }
+
+    /// Returns the index of the LED nearest to the normalized point (`x`, `y`).
+    ///
+    /// Both coordinates range from 0.0 (left/top) to 1.0 (right/bottom) across the whole device.
+    /// The device is divided into rows: each matrix zone gets as many rows as its matrix,
+    /// placed as in `matrix_layout()`, and every other zone gets a single row below them.
+    /// Within its rows, a zone is stretched across the full width,
+    /// with the LEDs of a non-matrix zone assumed to be evenly spaced from left to right.
+    /// As the protocol does not tell where LEDs are physically located, this is a heuristic.
+    ///
+    /// Returns `None` if the point is outside of the device or the device has no LEDs.
+    pub fn led_at_point(&self, x: f32, y: f32) -> Option<usize> {
+        if !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y) {
+            return None;
+        }
+        let layout = self.matrix_layout();
+        let matrix_rows = layout.iter().map(|p| p.height()).sum::<usize>();
+        let linear_zones = self
+            .get_all_zones()
+            .filter(|z| z.matrix().is_err() && z.num_leds() > 0)
+            .collect::<Vec<_>>();
+        let rows = (matrix_rows + linear_zones.len()) as f32;
+
+        // (x, y, led index) of the center of every LED
+        let mut points = Vec::new();
+        for placement in &layout {
+            let zone = self.get_zone(placement.zone_id()).ok()?;
+            let matrix = zone.matrix().ok()?;
+            for ((row, column), led) in matrix.enumerate_row_major() {
+                if *led as usize >= zone.num_leds() {
+                    // cells without an LED are marked with u32::MAX
+                    continue;
+                }
+                let px = (column as f32 + 0.5) / placement.width() as f32;
+                let py = (placement.row_offset() + row) as f32 + 0.5;
+                points.push((px, py / rows, zone.offset() + *led as usize));
+            }
+        }
+        for (i, zone) in linear_zones.iter().enumerate() {
+            let py = ((matrix_rows + i) as f32 + 0.5) / rows;
+            for led in 0..zone.num_leds() {
+                let px = (led as f32 + 0.5) / zone.num_leds() as f32;
+                points.push((px, py, zone.offset() + led));
+            }
+        }
+
+        points
+            .into_iter()
+            .map(|(px, py, led)| ((px - x).powi(2) + (py - y).powi(2), led))
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, led)| led)
+    }
+
+    /// Sets a single LED to the given `color`.
+    ///
+    /// When doing many writes in rapid succession, it is recommended to use the `cmd()` method instead.
+    pub async fn set_led(&self, led: usize, color: Color) -> OpenRgbResult<()> {
+        self.send_colors(LedTarget::Led(led), &[color], ColorOrigin::User)
+            .await
+    }
+
+    /// Sets all LEDs of this controller to a given `color`.
+    pub async fn set_all_leds(&self, color: Color) -> OpenRgbResult<()> {
+        self.set_leds_from(color).await
     }
 
     /// Sets the LEDs of this controller to the given `colors`.
+    ///
+    /// The colors are sent as given, even if there is not one color per LED.
+    /// Use `set_leds_from()` to pad or truncate them to the number of LEDs.
     pub async fn set_leds(&self, colors: impl IntoIterator<Item = Color>) -> OpenRgbResult<()> {
         let color_v = colors.into_iter().collect::<Vec<_>>();
-        self.proto
-            .update_leds(self.id as u32, color_v.as_slice())
-            .await
+        let span = tracing::info_span!(
+            "set_leds",
+            controller_id = self.id,
+            controller = self.name()
+        );
+        traced(
+            span,
+            self.send_colors(LedTarget::Controller, &color_v, ColorOrigin::User),
+        )
+        .await
+    }
+
+    /// Measures the average time it takes to send a full LED update to this controller.
+    ///
+    /// This writes to the device: it alternates between black and the current colors `iterations` times,
+    /// then restores the colors the controller reported before the benchmark.
+    /// Updates are not acknowledged by the server, so this measures how fast updates can be sent,
+    /// which is an upper bound for the frame rate.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `iterations` is 0 or any of the updates fails.
+    pub async fn benchmark_update(&mut self, iterations: u32) -> OpenRgbResult<Duration> {
+        if iterations == 0 {
+            return Err(OpenRgbError::CommandError(
+                "Benchmark needs at least one iteration".to_string(),
+            ));
+        }
+        self.sync_controller_data().await?;
+        let original = self.fit_to_leds(&self.data.colors);
+        let black = vec![Color::default(); original.len()];
+
+        let start = Instant::now();
+        for i in 0..iterations {
+            let frame = if i % 2 == 0 { &black } else { &original };
+            self.send_colors(LedTarget::Controller, frame, ColorOrigin::Device)
+                .await?;
+        }
+        let elapsed = start.elapsed();
+
+        self.send_colors(LedTarget::Controller, &original, ColorOrigin::Device)
+            .await?;
+        Ok(elapsed / iterations)
+    }
+
+    /// Sets the LEDs of this controller to the colors of `source`, which can be a slice, `Vec`, single `Color` or closure.
+    ///
+    /// The source always provides exactly one color per LED, see `ColorSource`.
+    /// Unlike `set_leds()`, which sends any number of colors as given, this is the entry point for everything
+    /// that should fit the LEDs of this controller, like `set_all_leds()` and `mirror_to()`.
+    pub async fn set_leds_from(&self, source: impl ColorSource) -> OpenRgbResult<()> {
+        self.set_leds(source.colors(self.num_leds())).await
+    }
+
+    /// Sets the LEDs of this controller to `colors` and waits until the server has read the update.
+    ///
+    /// LED updates are not acknowledged, so this sends a controller count request afterwards and waits for its answer.
+    /// The server handles requests on a connection in order, so once it answers, the update was processed.
+    /// This is a best-effort barrier: it does not guarantee the device itself has already shown the colors.
+    pub async fn set_leds_flushed(&self, colors: &[Color]) -> OpenRgbResult<()> {
+        self.set_leds(colors.iter().copied()).await?;
+        self.proto.get_controller_count().await?;
+        Ok(())
+    }
+
+    /// Syncs the colors of this controller and sets the LEDs of `target` to them.
+    ///
+    /// The colors are padded with black or truncated to the number of LEDs of `target`.
+    pub async fn mirror_to(&mut self, target: &Controller) -> OpenRgbResult<()> {
+        self.sync_controller_data().await?;
+        target.set_leds_from(self.data.colors.as_slice()).await
+    }
+
+    /// Sets every LED of this controller to the color returned by `f(index, num_leds)`.
+    pub async fn update_with<F>(&self, f: F) -> OpenRgbResult<()>
+    where
+        F: Fn(usize, usize) -> Color,
+    {
+        let num_leds = self.num_leds();
+        self.set_leds((0..num_leds).map(|i| f(i, num_leds))).await
+    }
+
+    /// Renders one frame of `effect` and sends it to this controller.
+    pub async fn render(&self, effect: &mut dyn Effect) -> OpenRgbResult<()> {
+        let colors = effect.render(&EffectContext::new(self));
+        self.set_leds(colors).await
+    }
+
+    /// Waits until `when`, then sets the LEDs of this controller to the given `colors`.
+    ///
+    /// Combined with synchronized clocks (e.g. through NTP), this can be used to roughly synchronize effects across machines.
+    /// The update is never sent before `when`, but it can arrive later due to scheduler delays and network jitter,
+    /// so expect an accuracy of a few milliseconds at best.
+    pub async fn set_leds_at(&self, colors: &[Color], when: Instant) -> OpenRgbResult<()> {
+        tokio::time::sleep_until(when).await;
+        self.set_leds(colors.iter().copied()).await
+    }
+
+    /// Sets the LEDs of multiple zones, sending one packet per zone.
+    ///
+    /// Unlike `cmd()`, which sends a single packet for the whole controller, this only touches the given zones.
+    /// If this controller has its own connection (see `connect_isolated()`), the zone updates are dispatched concurrently.
+    /// On a shared connection they are sent one after another, so they do not compete with other users of the connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the zones does not exist, in which case nothing is sent.
+    pub async fn set_zones_concurrent(
+        &self,
+        zone_colors: impl IntoIterator<Item = (usize, Vec<Color>)>,
+    ) -> OpenRgbResult<()> {
+        let zone_colors = zone_colors.into_iter().collect::<Vec<_>>();
+        for (zone_id, _) in &zone_colors {
+            self.get_zone(*zone_id)?;
+        }
+
+        if !self.isolated {
+            for (zone_id, colors) in zone_colors {
+                self.send_colors(LedTarget::Zone(zone_id), &colors, ColorOrigin::User)
+                    .await?;
+            }
+            return Ok(());
+        }
+
+        let mut writes = zone_colors
+            .iter()
+            .map(|(zone_id, colors)| {
+                self.prepare_write(LedTarget::Zone(*zone_id), colors, ColorOrigin::User)
+            })
+            .collect::<OpenRgbResult<Vec<_>>>()?;
+        // switch the mode before any of the zones is written
+        if let Some(first) = writes.first_mut() {
+            first.switch_mode().await?;
+        }
+        let mut tasks = tokio::task::JoinSet::new();
+        for write in writes {
+            tasks.spawn(write.send());
+        }
+        while let Some(result) = tasks.join_next().await {
+            result
+                .map_err(|e| OpenRgbError::CommandError(format!("Zone update failed: {e}")))??;
+        }
+        Ok(())
+    }
+
+    /// Sets the LEDs of this controller from tightly packed RGB bytes, such as a row of an image.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `pixels` does not contain exactly `3 * num_leds()` bytes.
+    pub async fn set_leds_from_rgb8(&self, pixels: &[u8]) -> OpenRgbResult<()> {
+        let colors = self.colors_from_bytes(pixels, 3)?;
+        self.set_leds_from(colors).await
+    }
+
+    /// Sets the LEDs of this controller from tightly packed RGBA bytes, the alpha channel is ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `pixels` does not contain exactly `4 * num_leds()` bytes.
+    pub async fn set_leds_from_rgba8(&self, pixels: &[u8]) -> OpenRgbResult<()> {
+        let colors = self.colors_from_bytes(pixels, 4)?;
+        self.set_leds_from(colors).await
+    }
+
+    /// Fades the LEDs of this controller from `from` to `to`.
+    ///
+    /// This sends `steps` frames, linearly interpolated per channel and spaced `interval` apart.
+    /// The last frame is always `to`.
+    /// If `from` or `to` do not have one color per LED, they are padded with black or truncated.
+    pub async fn crossfade(
+        &self,
+        from: &[Color],
+        to: &[Color],
+        steps: u32,
+        interval: Duration,
+    ) -> OpenRgbResult<()> {
+        let from = self.fit_to_leds(from);
+        let to = self.fit_to_leds(to);
+        for step in 1..=steps {
+            if step > 1 {
+                tokio::time::sleep(interval).await;
+            }
+            let frame = from
+                .iter()
+                .zip(to.iter())
+                .map(|(a, b)| lerp_color(*a, *b, step, steps));
+            self.set_leds(frame).await?;
+        }
+        Ok(())
+    }
+
+    /// Blinks all LEDs of this controller `times` times, alternating `color` and black every `interval`.
+    ///
+    /// The controller is set to a controllable mode first.
+    /// Afterwards, the previous mode and the colors last synced from the server are restored,
+    /// also if blinking failed partway.
+    pub async fn blink(&self, color: Color, times: u32, interval: Duration) -> OpenRgbResult<()> {
+        let previous_mode = self.active_mode()?.clone();
+        let shown = async {
+            self.set_controllable_mode().await?;
+            for _ in 0..times {
+                self.set_all_leds(color).await?;
+                tokio::time::sleep(interval).await;
+                self.set_all_leds(Color::default()).await?;
+                tokio::time::sleep(interval).await;
+            }
+            Ok(())
+        }
+        .await;
+        let restored = self.restore_state(&previous_mode).await;
+        shown.and(restored)
+    }
+
+    /// Pulses all LEDs of this controller `cycles` times, fading from black to `color` and back once every `period`.
+    ///
+    /// The controller is set to a controllable mode first.
+    /// Afterwards, the previous mode and the colors last synced from the server are restored,
+    /// also if pulsing failed partway.
+    pub async fn pulse(&self, color: Color, period: Duration, cycles: u32) -> OpenRgbResult<()> {
+        const STEPS: u32 = 16;
+        let previous_mode = self.active_mode()?.clone();
+        let interval = period / (2 * STEPS);
+        let shown = async {
+            self.set_controllable_mode().await?;
+            for _ in 0..cycles {
+                let fade_in = 1..=STEPS;
+                let fade_out = (0..STEPS).rev();
+                for step in fade_in.chain(fade_out) {
+                    self.set_all_leds(lerp_color(Color::default(), color, step, STEPS))
+                        .await?;
+                    tokio::time::sleep(interval).await;
+                }
+            }
+            Ok(())
+        }
+        .await;
+        let restored = self.restore_state(&previous_mode).await;
+        shown.and(restored)
+    }
+
+    /// Restores the colors last synced from the server and sets `mode` again.
+    ///
+    /// The mode is restored even if restoring the colors fails.
+    pub(crate) async fn restore_state(&self, mode: &ModeData) -> OpenRgbResult<()> {
+        let sent = self
+            .send_colors(
+                LedTarget::Controller,
+                &self.data.colors,
+                ColorOrigin::Device,
+            )
+            .await;
+        let restored = self.restore_mode(mode).await;
+        sent.and(restored)
+    }
+
+    /// Sets `mode` again, after it was replaced by a controllable mode.
+    pub(crate) async fn restore_mode(&self, mode: &ModeData) -> OpenRgbResult<()> {
+        self.mode_changed();
+        self.proto.update_mode(self.id as u32, mode).await
+    }
+
+    /// Pads `colors` with black or truncates it, so that it has exactly `num_leds()` colors.
+    fn fit_to_leds(&self, colors: &[Color]) -> Vec<Color> {
+        if colors.len() != self.num_leds() {
+            tracing::warn!(
+                "Controller {} was given {} colors, while its length is {}. Colors will be padded or truncated.",
+                self.name(),
+                colors.len(),
+                self.num_leds()
+            );
+        }
+        fit_colors(colors, self.num_leds())
+    }
+
+    fn colors_from_bytes(&self, pixels: &[u8], channels: usize) -> OpenRgbResult<Vec<Color>> {
+        if pixels.len() != channels * self.num_leds() {
+            return Err(OpenRgbError::CommandError(format!(
+                "Expected {} bytes ({channels} per LED) for controller {} with {} LEDs, got {}",
+                channels * self.num_leds(),
+                self.name(),
+                self.num_leds(),
+                pixels.len()
+            )));
+        }
+        let colors = pixels
+            .chunks_exact(channels)
+            .map(|px| Color::new(px[0], px[1], px[2]))
+            .collect();
+        Ok(colors)
     }
 
     /// Sets the LEDs of a specific zone to the given `colors`.
@@ -185,8 +1327,146 @@ impl Controller {
         colors: impl IntoIterator<Item = Color>,
     ) -> OpenRgbResult<()> {
         let color_v = colors.into_iter().collect::<Vec<_>>();
-        self.proto
-            .update_zone_leds(self.id as u32, zone_id as u32, &color_v)
+        self.send_colors(LedTarget::Zone(zone_id), &color_v, ColorOrigin::User)
+            .await
+    }
+
+    /// Edits the colors of this controller in place, only sending the LEDs that changed.
+    ///
+    /// `f` is given a [`FrameBuffer`] holding the current colors from `data().colors`.
+    /// Afterwards, every changed single LED is sent as a single LED update,
+    /// and longer changed spans are sent as an update of every zone they overlap.
+    /// Nothing is sent if no LED changed. The cached colors are updated to the new colors.
+    pub async fn transaction<F>(&mut self, f: F) -> OpenRgbResult<()>
+    where
+        F: FnOnce(&mut FrameBuffer),
+    {
+        let mut buffer = FrameBuffer::new(self.fit_to_leds(&self.data.colors));
+        f(&mut buffer);
+
+        // unchanged LEDs keep the colors of the device, only the changed ones are user colors
+        let spans = buffer.changed_spans();
+        let mut colors = buffer.into_colors();
+        for span in &spans {
+            for color in &mut colors[span.clone()] {
+                *color = self.transform(*color);
+            }
+        }
+
+        let mut zones = Vec::new();
+        for span in spans {
+            if span.len() == 1 {
+                let target = LedTarget::Led(span.start);
+                self.send_colors(target, &colors[span.start..span.end], ColorOrigin::Device)
+                    .await?;
+                continue;
+            }
+            for zone in self.get_all_zones() {
+                let range = zone.offset()..zone.offset() + zone.num_leds();
+                let overlaps = range.start < span.end && span.start < range.end;
+                if overlaps && !zones.contains(&zone.zone_id()) {
+                    zones.push(zone.zone_id());
+                    let target = LedTarget::Zone(zone.zone_id());
+                    self.send_colors(target, &colors[range], ColorOrigin::Device)
+                        .await?;
+                }
+            }
+        }
+        self.data.colors = colors;
+        Ok(())
+    }
+
+    /// Returns the current colors of the zone with the given `zone_id`.
+    ///
+    /// The colors are read from the cached controller data, use `sync_controller_data()` to refresh them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the zone does not exist or the controller reported fewer colors than it has LEDs.
+    pub fn zone_colors(&self, zone_id: usize) -> OpenRgbResult<&[Color]> {
+        let zone = self.get_zone(zone_id)?;
+        let end = zone.offset() + zone.num_leds();
+        self.data
+            .colors
+            .get(zone.offset()..end)
+            .ok_or(OpenRgbError::ProtocolError(format!(
+                "Controller {} reported {} colors, but zone {zone_id} ends at LED {end}",
+                self.name(),
+                self.data.colors.len()
+            )))
+    }
+
+    /// Syncs the controller data and returns the current colors of every zone, in the order of `data().zones`.
+    ///
+    /// If the controller reported fewer colors than it has LEDs, the missing colors are padded with black.
+    pub async fn all_zone_colors(&mut self) -> OpenRgbResult<Vec<Vec<Color>>> {
+        self.sync_controller_data().await?;
+        let colors = self
+            .get_all_zones()
+            .map(|zone| {
+                let start = zone.offset().min(self.data.colors.len());
+                let end = (zone.offset() + zone.num_leds()).min(self.data.colors.len());
+                zone.fit_colors(&self.data.colors[start..end])
+            })
+            .collect();
+        Ok(colors)
+    }
+
+    /// Syncs the current colors and sets only the LEDs where `mask` is true to the corresponding color in `colors`.
+    ///
+    /// The other LEDs keep the color the controller reported, and the merged colors are sent in one update.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `colors` or `mask` does not have exactly one entry per LED.
+    pub async fn set_leds_masked(&mut self, colors: &[Color], mask: &[bool]) -> OpenRgbResult<()> {
+        if colors.len() != self.num_leds() || mask.len() != self.num_leds() {
+            return Err(OpenRgbError::CommandError(format!(
+                "Controller {} has {} LEDs, got {} colors and {} mask entries",
+                self.name(),
+                self.num_leds(),
+                colors.len(),
+                mask.len()
+            )));
+        }
+        self.sync_controller_data().await?;
+        let merged = self
+            .fit_to_leds(&self.data.colors)
+            .into_iter()
+            .zip(colors.iter().zip(mask))
+            .map(|(current, (color, set))| {
+                if *set {
+                    self.transform(*color)
+                } else {
+                    current
+                }
+            })
+            .collect::<Vec<_>>();
+        // the merged colors are device colors, with gamma and brightness already applied to the new ones
+        self.send_colors(LedTarget::Controller, &merged, ColorOrigin::Device)
+            .await?;
+        self.data.colors = merged;
+        Ok(())
+    }
+
+    /// Sets the colors of the zone with the given `zone_id`.
+    ///
+    /// Together with `zone_colors()`, this allows read-modify-write updates of a single zone.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the zone does not exist or `colors` does not contain exactly one color per LED in the zone.
+    pub async fn set_zone_colors(&self, zone_id: usize, colors: &[Color]) -> OpenRgbResult<()> {
+        let zone = self.get_zone(zone_id)?;
+        if colors.len() != zone.num_leds() {
+            return Err(OpenRgbError::CommandError(format!(
+                "Zone {zone_id} for controller {} has {} LEDs, got {} colors",
+                self.name(),
+                zone.num_leds(),
+                colors.len()
+            )));
+        }
+        self.send_colors(LedTarget::Zone(zone_id), colors, ColorOrigin::User)
             .await
     }
 
@@ -201,6 +1481,13 @@ impl Controller {
         self.set_all_leds(Color { r: 0, g: 0, b: 0 }).await
     }
 
+    /// Starts buffering single LED updates, which are sent as one packet when the returned guard ends or is dropped.
+    ///
+    /// This cuts the number of packets for code that sets adjacent LEDs one by one, see `CoalescingGuard`.
+    pub fn begin_coalescing(&self) -> CoalescingGuard<'_> {
+        CoalescingGuard::new(self)
+    }
+
     /// Creates an `UpdateLedCommand` for this controller.
     ///
     /// Controller LEDs can be updated in three ways:
@@ -214,22 +1501,27 @@ impl Controller {
     ///
     /// # Example
     /// ```no_run
+    /// # use openrgb::{Color, OpenRgbClient, OpenRgbResult};
+    /// # #[tokio::main]
+    /// # async fn main() -> OpenRgbResult<()> {
     /// // let's say we have a controller with 5 LEDs
-    /// let mut client = OpenRgbClient::connect().await?;
+    /// let client = OpenRgbClient::connect().await?;
     /// let controller = client.get_controller(0).await?;
     ///
     /// // direct write
-    /// controller.set_leds([Color::new(255, 0, 0); 5)]).await?;
+    /// controller.set_leds([Color::new(255, 0, 0); 5]).await?;
     ///
     /// // equivalent with command
     /// let mut cmd = controller.cmd();
-    /// cmd.add_update_led(0, Color::new(255, 0, 0))?;
-    /// cmd.add_update_led(2, Color::new(255, 0, 0))?; // order doesn't matter
-    /// cmd.add_update_led(4, Color::new(255, 0, 0))?;
-    /// cmd.add_update_led(1, Color::new(255, 0, 0))?;
-    /// cmd.add_update_led(5, Color::new(255, 0, 0))?;
+    /// cmd.add_set_led(0, Color::new(255, 0, 0))?;
+    /// cmd.add_set_led(2, Color::new(255, 0, 0))?; // order doesn't matter
+    /// cmd.add_set_led(4, Color::new(255, 0, 0))?;
+    /// cmd.add_set_led(1, Color::new(255, 0, 0))?;
+    /// cmd.add_set_led(3, Color::new(255, 0, 0))?;
     /// // this is just a single update
     /// cmd.execute().await?;
+    /// # Ok(())
+    /// # }
     /// ```
     ///
     /// This is especially useful for devices with multiple zones that should animate separately.
@@ -237,6 +1529,25 @@ impl Controller {
         UpdateLedCommand::new(self)
     }
 
+    /// Returns the id of the zone that contains the LED with the given controller-wide `index`.
+    ///
+    /// Returns `None` if the index is out of bounds.
+    pub fn led_zone(&self, index: usize) -> Option<usize> {
+        (0..self.data.zones.len()).find(|zone_id| {
+            self.zone_range(*zone_id)
+                .is_some_and(|range| range.contains(&index))
+        })
+    }
+
+    /// Returns the range of controller-wide LED indices of the zone with the given `zone_id`.
+    ///
+    /// Returns `None` if the zone does not exist.
+    pub fn zone_range(&self, zone_id: usize) -> Option<std::ops::Range<usize>> {
+        let offset = self.get_zone_led_offset(zone_id).ok()?;
+        let len = self.data.zones[zone_id].leds_count as usize;
+        Some(offset..offset + len)
+    }
+
     pub(crate) fn get_zone_led_offset(&self, zone_id: usize) -> OpenRgbResult<usize> {
         if zone_id >= self.data.zones.len() {
             return Err(OpenRgbError::ProtocolError(format!(
@@ -264,14 +1575,1134 @@ impl Controller {
         self.data = data;
         Ok(())
     }
+
+    /// Returns a receiver that gets the name of the active mode whenever it changes, e.g. from the OpenRGB GUI.
+    ///
+    /// The server does not notify clients of mode changes, so a background task fetches the controller data
+    /// every `interval` and compares the active mode to the previous one, starting with the cached active mode.
+    /// Polling uses the connection of this controller and stops once the receiver is dropped.
+    /// If fetching fails, polling stops and the receiver returns `None` once the received changes are read.
+    ///
+    /// The receiver buffers up to `WATCH_MODE_BUFFER` changes. When it is full, polling pauses
+    /// until changes are read, so a slow reader never misses a change.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime, since the polling task is spawned on the current runtime.
+    pub fn watch_mode(&self, interval: Duration) -> tokio::sync::mpsc::Receiver<String> {
+        let (sender, receiver) = tokio::sync::mpsc::channel(WATCH_MODE_BUFFER);
+        let proto = self.proto.clone();
+        let id = self.id as u32;
+        let mut previous = self.data.active_mode().map(|m| m.name.clone());
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval_at(Instant::now() + interval, interval);
+            ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            while !sender.is_closed() {
+                ticks.tick().await;
+                let data = match proto.get_controller(id).await {
+                    Ok(data) => data,
+                    Err(e) => {
+                        tracing::warn!("Stopped watching mode of controller {id}: {e}");
+                        return;
+                    }
+                };
+                let current = data.active_mode().map(|m| m.name.clone());
+                if current != previous {
+                    if let Some(name) = &current
+                        && sender.send(name.clone()).await.is_err()
+                    {
+                        return;
+                    }
+                    previous = current;
+                }
+            }
+        });
+        receiver
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::OpenRgbClient;
+    use crate::PacketId;
+    use crate::data::{ProtocolOption, ZoneType};
+    use crate::protocol::mock::{MockServer, controller_fixture, mode_fixture};
+    use array2d::Array2D;
+    use tracing_test::traced_test;
 
     use super::*;
 
+    /// Reads the colors of every `RGBControllerUpdateLeds` packet.
+    fn update_leds_frames(mock: &MockServer) -> Vec<Vec<Color>> {
+        mock.packets_of(PacketId::RGBControllerUpdateLeds)
+            .iter()
+            .map(|p| {
+                let mut msg = p.message(5);
+                let _data_size = msg.read_u32().unwrap();
+                msg.read_value::<Vec<Color>>().unwrap()
+            })
+            .collect()
+    }
+
+    /// Reads the colors of the last `RGBControllerUpdateLeds` packet.
+    fn update_leds_colors(mock: &MockServer) -> Vec<Color> {
+        update_leds_frames(mock).pop().expect("no update packet")
+    }
+
+    #[tokio::test]
+    async fn test_set_leds_from_rgb8() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        controller.set_leds_from_rgb8(&[1, 2, 3, 4, 5, 6]).await?;
+        mock.wait_for_packets(2).await;
+        assert_eq!(
+            update_leds_colors(&mock),
+            vec![Color::new(1, 2, 3), Color::new(4, 5, 6)]
+        );
+
+        controller
+            .set_leds_from_rgba8(&[1, 2, 3, 255, 4, 5, 6, 255])
+            .await?;
+        mock.wait_for_packets(3).await;
+        assert_eq!(
+            update_leds_colors(&mock),
+            vec![Color::new(1, 2, 3), Color::new(4, 5, 6)]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_leds_from_rgb8_length() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        assert!(controller.set_leds_from_rgb8(&[1, 2, 3]).await.is_err());
+        assert!(controller.set_leds_from_rgb8(&[0; 7]).await.is_err());
+        assert!(controller.set_leds_from_rgba8(&[0; 6]).await.is_err());
+        assert!(
+            mock.packets_of(PacketId::RGBControllerUpdateLeds)
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_leds_at() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        let when = Instant::now() + Duration::from_millis(100);
+        let colors = [Color::new(255, 0, 0); 2];
+        let task = tokio::spawn(async move { controller.set_leds_at(&colors, when).await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(
+            mock.packets_of(PacketId::RGBControllerUpdateLeds)
+                .is_empty()
+        );
+
+        task.await.unwrap()?;
+        assert!(Instant::now() >= when);
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 1)
+            .await;
+        assert_eq!(update_leds_colors(&mock), colors.to_vec());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_strict_addressable_mode() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[3]);
+        data.active_mode = 1;
+        let mock = MockServer::start(5, vec![data]).await;
+        let mut controller = mock.client().await.get_controller(0).await?;
+        let red = Color::new(255, 0, 0);
+
+        // off by default
+        assert!(!controller.strict_addressable_mode());
+        controller.set_led(0, red).await?;
+
+        controller.set_strict_addressable_mode(true);
+        let err = controller.set_leds([red; 3]).await.unwrap_err();
+        assert!(err.to_string().contains("mode Static"), "{err}");
+        assert!(controller.set_led(0, red).await.is_err());
+        assert!(
+            mock.packets_of(PacketId::RGBControllerUpdateLeds)
+                .is_empty()
+        );
+
+        controller.set_controllable_mode().await?;
+        controller.sync_controller_data().await?;
+        controller.set_leds([red; 3]).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_auto_controllable() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[3]);
+        data.active_mode = 1;
+        let mock = MockServer::start(5, vec![data]).await;
+        let mut controller = mock.client().await.get_controller(0).await?;
+        let red = Color::new(255, 0, 0);
+        controller.auto_controllable(true);
+
+        controller.set_leds([red; 3]).await?;
+        controller.set_leds([red; 3]).await?;
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 2)
+            .await;
+        let modes = mock.packets_of(PacketId::RGBControllerUpdateMode);
+        assert_eq!(modes.len(), 1);
+
+        // changing the mode makes the next update set a controllable mode again
+        controller.set_mode_by_name("Static").await?;
+        controller.set_led(0, red).await?;
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateSingleLed, 1)
+            .await;
+        assert_eq!(mock.packets_of(PacketId::RGBControllerUpdateMode).len(), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_mode_with_colors() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2])]).await;
+        let mut controller = mock.client().await.get_controller(0).await?;
+        let blue = Color::new(0, 0, 255);
+
+        assert!(
+            controller
+                .set_mode_with_colors("static", vec![blue; 2])
+                .await
+                .is_err()
+        );
+        assert!(
+            controller
+                .set_mode_with_colors("Rainbow", vec![blue])
+                .await
+                .is_err()
+        );
+        controller
+            .set_mode_with_colors("static", vec![blue])
+            .await?;
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateMode, 1)
+            .await;
+        assert_eq!(packets.len(), 1);
+
+        controller.sync_controller_data().await?;
+        let mode = controller.active_mode()?;
+        assert_eq!(mode.name, "Static");
+        assert_eq!(mode.colors, vec![blue]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_has_alt_names() -> OpenRgbResult<()> {
+        let mut empty = controller_fixture("Empty", &[2]);
+        empty.led_alt_names = ProtocolOption::Some(vec![String::new(); 2]);
+        let mut named = controller_fixture("Named", &[2]);
+        named.led_alt_names = ProtocolOption::Some(vec!["Logo".to_string(), String::new()]);
+        named.flags = ProtocolOption::Some(ControllerFlags::IsLocal.into());
+        let mock = MockServer::start(5, vec![controller_fixture("None", &[2]), empty, named]).await;
+        let client = mock.client().await;
+
+        let none = client.get_controller(0).await?;
+        assert!(none.data().led_alt_names.is_supported());
+        assert!(!none.has_alt_names());
+        assert!(!none.has_flags());
+        assert!(!client.get_controller(1).await?.has_alt_names());
+        let named = client.get_controller(2).await?;
+        assert!(named.has_alt_names());
+        assert!(named.has_flags());
+
+        let mock = MockServer::start(4, vec![controller_fixture("Old", &[2])]).await;
+        let old = mock.client().await.get_controller(0).await?;
+        assert!(!old.data().led_alt_names.is_supported());
+        assert!(!old.has_alt_names());
+        assert!(!old.has_flags());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mirror_to() -> OpenRgbResult<()> {
+        let mut source = controller_fixture("Source", &[3]);
+        let colors = vec![
+            Color::new(1, 1, 1),
+            Color::new(2, 2, 2),
+            Color::new(3, 3, 3),
+        ];
+        source.colors = colors.clone();
+        let controllers = vec![
+            source,
+            controller_fixture("Short", &[2]),
+            controller_fixture("Long", &[4]),
+        ];
+        let mock = MockServer::start(5, controllers).await;
+        let client = mock.client().await;
+        let mut source = client.get_controller(0).await?;
+
+        source.mirror_to(&client.get_controller(1).await?).await?;
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 1)
+            .await;
+        assert_eq!(update_leds_colors(&mock), colors[..2].to_vec());
+
+        source.mirror_to(&client.get_controller(2).await?).await?;
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 2)
+            .await;
+        let mut padded = colors.clone();
+        padded.push(Color::default());
+        assert_eq!(update_leds_colors(&mock), padded);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_history() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2])]).await;
+        let mut controller = mock.client().await.get_controller(0).await?;
+        controller.set_leds([Color::new(9, 9, 9); 2]).await?;
+        assert!(controller.with_history_frames(|h| h.is_empty()));
+
+        controller.with_history(2);
+        let frames = (1..=3u8)
+            .map(|i| vec![Color::new(i, i, i); 2])
+            .collect::<Vec<_>>();
+        for frame in &frames {
+            controller.set_leds(frame.clone()).await?;
+        }
+        controller.with_history_frames(|h| assert_eq!(h, &frames[1..]));
+
+        controller.with_history(1);
+        controller.with_history_frames(|h| assert_eq!(h, &frames[2..]));
+        controller.with_history(0);
+        assert!(controller.with_history_frames(|h| h.is_empty()));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_leds_flushed() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+        let before = mock.all_packets().len();
+
+        controller
+            .set_leds_flushed(&[Color::new(255, 0, 0); 2])
+            .await?;
+        // the request is answered, so both packets were already received
+        let ids = mock.all_packets()[before..]
+            .iter()
+            .map(|p| p.packet_id)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            vec![
+                PacketId::RGBControllerUpdateLeds,
+                PacketId::RequestControllerCount
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_leds_from() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[3])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+        let red = Color::new(255, 0, 0);
+        let black = Color::default();
+
+        controller.set_leds_from([red].as_slice()).await?;
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 1)
+            .await;
+        assert_eq!(update_leds_colors(&mock), vec![red, black, black]);
+
+        controller.set_leds_from(vec![red; 5]).await?;
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 2)
+            .await;
+        assert_eq!(update_leds_colors(&mock), vec![red; 3]);
+
+        controller.set_leds_from(red).await?;
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 3)
+            .await;
+        assert_eq!(update_leds_colors(&mock), vec![red; 3]);
+
+        controller
+            .set_leds_from(|i: usize| Color::new(i as u8, 0, 0))
+            .await?;
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 4)
+            .await;
+        assert_eq!(
+            update_leds_colors(&mock),
+            vec![
+                Color::new(0, 0, 0),
+                Color::new(1, 0, 0),
+                Color::new(2, 0, 0)
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_benchmark_update() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[3]);
+        data.colors = vec![Color::new(10, 20, 30); 3];
+        let mock = MockServer::start(5, vec![data]).await;
+        let mut controller = mock.client().await.get_controller(0).await?;
+
+        let average = controller.benchmark_update(4).await?;
+        assert!(average > Duration::ZERO);
+        assert!(average < Duration::from_secs(1));
+
+        // 4 benchmark frames and the restore
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 5)
+            .await;
+        assert_eq!(update_leds_colors(&mock), vec![Color::new(10, 20, 30); 3]);
+        assert!(controller.benchmark_update(0).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_leds_masked() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[4]);
+        let prior = vec![
+            Color::new(1, 1, 1),
+            Color::new(2, 2, 2),
+            Color::new(3, 3, 3),
+            Color::new(4, 4, 4),
+        ];
+        data.colors = prior.clone();
+        let mock = MockServer::start(5, vec![data]).await;
+        let mut controller = mock.client().await.get_controller(0).await?;
+        controller.data.colors = vec![Color::default(); 4];
+
+        let red = Color::new(255, 0, 0);
+        controller
+            .set_leds_masked(&[red; 4], &[true, false, true, false])
+            .await?;
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 1)
+            .await;
+        let expected = vec![red, prior[1], red, prior[3]];
+        assert_eq!(update_leds_colors(&mock), expected);
+        assert_eq!(controller.data().colors, expected);
+
+        assert!(
+            controller
+                .set_leds_masked(&[red; 4], &[true; 3])
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_led_color() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[3]);
+        data.colors[1] = Color::new(10, 20, 30);
+        let mut unpopulated = controller_fixture("Unpopulated", &[3]);
+        unpopulated.colors.clear();
+        let mock = MockServer::start(5, vec![data, unpopulated]).await;
+        let client = mock.client().await;
+
+        let mut controller = client.get_controller(0).await?;
+        assert_eq!(controller.led_color(1).await?, Color::new(10, 20, 30));
+        assert_eq!(controller.led_color(2).await?, Color::default());
+        assert!(controller.led_color(3).await.is_err());
+
+        let mut controller = client.get_controller(1).await?;
+        assert!(controller.led_color(0).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validate_led_counts() -> OpenRgbResult<()> {
+        let mut missing_led = controller_fixture("Missing LED", &[2, 3]);
+        missing_led.leds.pop();
+        let mut missing_color = controller_fixture("Missing color", &[2, 3]);
+        missing_color.colors.pop();
+        let mut bad_matrix = controller_fixture("Bad matrix", &[2, 3]);
+        bad_matrix.zones[0].matrix = Some(Array2D::from_row_major(&[0, 2], 1, 2).unwrap());
+        let controllers = vec![
+            controller_fixture("Strip", &[2, 3]),
+            missing_led,
+            missing_color,
+            bad_matrix,
+        ];
+        let mock = MockServer::start(5, controllers).await;
+        let client = mock.client().await;
+
+        assert!(
+            client
+                .get_controller(0)
+                .await?
+                .validate_led_counts()
+                .is_ok()
+        );
+        for (id, expected) in [
+            (1, "reported 4 LEDs, but its zones have 5 LEDs"),
+            (2, "reported 4 colors, but its zones have 5 LEDs"),
+            (3, "refers to LED 2, but the zone has LEDs 0..2"),
+        ] {
+            let err = client
+                .get_controller(id)
+                .await?
+                .validate_led_counts()
+                .unwrap_err();
+            assert!(matches!(err, OpenRgbError::ProtocolError(_)));
+            assert!(err.to_string().contains(expected), "{err}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_active_mode() -> OpenRgbResult<()> {
+        let mut invalid = controller_fixture("Invalid", &[1]);
+        invalid.active_mode = -3;
+        let controllers = vec![controller_fixture("Strip", &[1]), invalid];
+        let mock = MockServer::start(5, controllers).await;
+        let client = mock.client().await;
+
+        assert_eq!(
+            client.get_controller(0).await?.active_mode()?.name(),
+            "Direct"
+        );
+        let mut controller = client.get_controller(1).await?;
+        assert!(controller.active_mode().is_err());
+        assert!(controller.save_mode().await.is_err());
+        assert!(controller.set_active_mode_speed(1).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_led_counts() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[10, 20]);
+        data.leds.truncate(25);
+        data.colors.truncate(20);
+        let mock = MockServer::start(5, vec![data]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        assert_eq!(controller.declared_led_count(), 30);
+        assert_eq!(controller.led_struct_count(), 25);
+        assert_eq!(controller.color_buffer_len(), 20);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_mode_by_name() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[1])]).await;
+        let mut controller = mock.client().await.get_controller(0).await?;
+
+        controller.set_mode_by_name("static").await?;
+        controller.sync_controller_data().await?;
+        assert_eq!(controller.active_mode()?.name(), "Static");
+        assert!(controller.set_mode_by_name("Rainbow").await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_controllable_mode_protocol_2() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[1]);
+        data.active_mode = 1;
+        let mock = MockServer::start(2, vec![data]).await;
+        let mut controller = mock.client().await.get_controller(0).await?;
+
+        controller.set_controllable_mode().await?;
+        controller.turn_off_leds().await?;
+        controller.sync_controller_data().await?;
+        assert_eq!(controller.active_mode()?.name(), "Direct");
+        assert!(mock.packets_of(PacketId::RGBControllerSaveMode).is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_mode_by_value() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[1]);
+        data.modes[0].value = 0x10;
+        data.modes[1].value = 0x20;
+        let mock = MockServer::start(5, vec![data]).await;
+        let mut controller = mock.client().await.get_controller(0).await?;
+
+        controller.set_mode_by_value(0x20).await?;
+        controller.sync_controller_data().await?;
+        assert_eq!(controller.active_mode()?.name(), "Static");
+        assert!(controller.set_mode_by_value(0x30).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_set_mode_by_name_span() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[1])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        assert!(controller.set_mode_by_name("Rainbow").await.is_err());
+        assert!(logs_contain(
+            "set_mode_by_name{controller_id=0 controller=\"Strip\"}"
+        ));
+        assert!(logs_contain("has no mode named Rainbow"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_led_at_point() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Keyboard", &[2, 6]);
+        data.zones[1].zone_type = ZoneType::Matrix;
+        data.zones[1].matrix = Some(Array2D::from_row_major(&[0, 1, 2, 3, 4, 5], 2, 3).unwrap());
+        let mock = MockServer::start(5, vec![data]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        // 3 rows: two matrix rows, followed by the linear zone
+        // center of the matrix cell at row 1, column 2, which is LED 5 of zone 1
+        assert_eq!(controller.led_at_point(2.5 / 3.0, 1.5 / 3.0), Some(2 + 5));
+        assert_eq!(controller.led_at_point(0.0, 0.0), Some(2));
+        // the linear zone is stretched across the bottom row
+        assert_eq!(controller.led_at_point(0.1, 1.0), Some(0));
+        assert_eq!(controller.led_at_point(0.9, 1.0), Some(1));
+        assert_eq!(controller.led_at_point(1.5, 0.5), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_update_with() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2, 3])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        controller
+            .update_with(|i, n| {
+                let v = (i * 255 / (n - 1)) as u8;
+                Color::new(v, 0, 255 - v)
+            })
+            .await?;
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 1)
+            .await;
+        let colors = update_leds_colors(&mock);
+        assert_eq!(colors.len(), 5);
+        assert_eq!(colors[0], Color::new(0, 0, 255));
+        assert_eq!(colors[4], Color::new(255, 0, 0));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_crossfade() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        let from = [Color::new(0, 100, 200), Color::new(10, 20, 30)];
+        let to = [Color::new(200, 50, 0), Color::new(30, 40, 50)];
+        controller
+            .crossfade(&from, &to, 2, Duration::from_millis(1))
+            .await?;
+        mock.wait_for_packets(3).await;
+
+        let frames = update_leds_frames(&mock);
+        assert_eq!(
+            frames,
+            vec![
+                vec![Color::new(100, 75, 100), Color::new(20, 30, 40)],
+                to.to_vec(),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_crossfade_pads() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        let to = [Color::new(100, 100, 100); 3];
+        controller.crossfade(&[], &to, 1, Duration::ZERO).await?;
+        mock.wait_for_packets(2).await;
+
+        assert_eq!(
+            update_leds_colors(&mock),
+            vec![Color::new(100, 100, 100); 2]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blink() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        let red = Color::new(255, 0, 0);
+        controller.blink(red, 2, Duration::ZERO).await?;
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 5)
+            .await;
+
+        let black = Color::default();
+        let restored = controller.data().colors.clone();
+        assert_eq!(
+            update_leds_frames(&mock),
+            vec![
+                vec![red; 2],
+                vec![black; 2],
+                vec![red; 2],
+                vec![black; 2],
+                restored
+            ]
+        );
+        // controllable mode and the restored previous mode
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateMode, 2)
+            .await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blink_restores_on_error() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[2]);
+        data.active_mode = 1;
+        let mock = MockServer::start(5, vec![data]).await;
+        let client = mock.client().await;
+        let mut controller = client.get_controller(0).await?;
+        // the cached mode stays Static, so the first frame fails
+        controller.set_strict_addressable_mode(true);
+
+        let red = Color::new(255, 0, 0);
+        assert!(controller.blink(red, 2, Duration::ZERO).await.is_err());
+        assert!(controller.pulse(red, Duration::ZERO, 1).await.is_err());
+        // controllable mode and the restored previous mode, for both
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateMode, 4)
+            .await;
+        assert_eq!(client.get_controller(0).await?.data().active_mode, 1);
+        assert!(
+            mock.packets_of(PacketId::RGBControllerUpdateLeds)
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pulse() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[1])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        let color = Color::new(160, 80, 0);
+        controller.pulse(color, Duration::ZERO, 1).await?;
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 33)
+            .await;
+
+        let frames = update_leds_frames(&mock);
+        assert_eq!(frames[15], vec![color]);
+        assert_eq!(frames[16], vec![Color::new(150, 75, 0)]);
+        assert_eq!(frames[31], vec![Color::default()]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_is_addressable() -> OpenRgbResult<()> {
+        let strip = controller_fixture("Strip", &[2]);
+        let empty = controller_fixture("Empty", &[]);
+        let mut fixed = controller_fixture("Fixed", &[2]);
+        fixed.modes.remove(0);
+        let mock = MockServer::start(5, vec![strip, empty, fixed]).await;
+        let client = mock.client().await;
+
+        assert!(client.get_controller(0).await?.is_addressable());
+        assert!(!client.get_controller(1).await?.is_addressable());
+        assert!(!client.get_controller(2).await?.is_addressable());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_identity_accessors() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[1]);
+        data.serial = String::new();
+        let mock = MockServer::start(5, vec![data]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        assert_eq!(controller.vendor(), Some("Vendor"));
+        assert_eq!(controller.version(), Some("1.0"));
+        assert_eq!(controller.serial(), None);
+        assert_eq!(controller.location(), Some("Location"));
+        Ok(())
+    }
+
+    /// Reads the zone ids and colors of every `RGBControllerUpdateZoneLeds` packet, sorted by zone id.
+    fn update_zone_leds_packets(mock: &MockServer) -> Vec<(u32, Vec<Color>)> {
+        let mut zones = mock
+            .packets_of(PacketId::RGBControllerUpdateZoneLeds)
+            .iter()
+            .map(|p| {
+                let mut msg = p.message(5);
+                let _data_size = msg.read_u32().unwrap();
+                let zone_id = msg.read_u32().unwrap();
+                (zone_id, msg.read_value::<Vec<Color>>().unwrap())
+            })
+            .collect::<Vec<_>>();
+        zones.sort_by_key(|(zone_id, _)| *zone_id);
+        zones
+    }
+
+    #[tokio::test]
+    async fn test_set_zones_concurrent() -> OpenRgbResult<()> {
+        let red = Color::new(255, 0, 0);
+        let blue = Color::new(0, 0, 255);
+        let expected = vec![(0, vec![red; 2]), (2, vec![blue; 4])];
+
+        for isolate in [false, true] {
+            let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2, 3, 4])]).await;
+            let mut controller = mock.client().await.get_controller(0).await?;
+            if isolate {
+                controller = controller.connect_isolated().await?;
+            }
+            assert_eq!(controller.is_isolated(), isolate);
+
+            controller
+                .set_zones_concurrent([(2, vec![blue; 4]), (0, vec![red; 2])])
+                .await?;
+            mock.wait_for_packets_of(PacketId::RGBControllerUpdateZoneLeds, 2)
+                .await;
+            assert_eq!(update_zone_leds_packets(&mock), expected);
+
+            assert!(
+                controller
+                    .set_zones_concurrent([(3, vec![red])])
+                    .await
+                    .is_err()
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_flags() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[1]);
+        data.flags
+            .replace(ControllerFlags::IsRemote | ControllerFlags::IsVirtual);
+        let mock = MockServer::start(5, vec![data]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        assert_eq!(
+            controller.flags(),
+            ControllerFlags::IsRemote | ControllerFlags::IsVirtual
+        );
+        assert!(!controller.is_local());
+        assert!(controller.is_remote());
+        assert!(controller.is_virtual());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cycle_mode() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[1]);
+        data.active_mode = 1;
+        let mock = MockServer::start(5, vec![data]).await;
+        let mut controller = mock.client().await.get_controller(0).await?;
+
+        assert_eq!(controller.next_mode().await?, "Direct");
+        assert_eq!(controller.data().active_mode, 0);
+        assert_eq!(controller.prev_mode().await?, "Static");
+        assert_eq!(controller.data().active_mode, 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_led_value() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[3]);
+        data.leds[2].value = 0xAB;
+        let mock = MockServer::start(5, vec![data]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        assert_eq!(controller.led_value(0)?, 0);
+        assert_eq!(controller.led_value(2)?, 0xAB);
+        assert!(controller.led_value(3).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_active_mode_speed() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[1]);
+        let mut rainbow = mode_fixture("Rainbow", ModeFlag::HasSpeed | ModeFlag::HasDirectionLR);
+        rainbow.speed_max = 100;
+        rainbow.index = 2;
+        data.modes.push(rainbow);
+        data.active_mode = 2;
+        let mock = MockServer::start(5, vec![data]).await;
+        let mut controller = mock.client().await.get_controller(0).await?;
+
+        controller.set_active_mode_speed(50).await?;
+        assert_eq!(controller.data().active_mode().unwrap().speed(), Some(50));
+        controller
+            .set_active_mode_direction(Direction::Right)
+            .await?;
+        assert_eq!(
+            controller.data().active_mode().unwrap().direction,
+            Direction::Right
+        );
+        assert!(
+            controller
+                .set_active_mode_direction(Direction::Up)
+                .await
+                .is_err()
+        );
+        assert!(controller.set_active_mode_brightness(10).await.is_err());
+
+        controller.next_mode().await?;
+        assert!(controller.set_active_mode_speed(10).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transaction_single_leds() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2, 3])]).await;
+        let mut controller = mock.client().await.get_controller(0).await?;
+
+        let red = Color::new(255, 0, 0);
+        controller
+            .transaction(|buffer| {
+                buffer.set(0, red).unwrap();
+                buffer.set(3, red).unwrap();
+            })
+            .await?;
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateSingleLed, 2)
+            .await;
+        let leds = packets
+            .iter()
+            .map(|p| {
+                let mut msg = p.message(5);
+                (msg.read_u32().unwrap(), msg.read_value::<Color>().unwrap())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(leds, vec![(0, red), (3, red)]);
+        assert!(
+            mock.packets_of(PacketId::RGBControllerUpdateLeds)
+                .is_empty()
+        );
+        assert!(
+            mock.packets_of(PacketId::RGBControllerUpdateZoneLeds)
+                .is_empty()
+        );
+        assert_eq!(controller.data().colors[3], red);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transaction_span() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2, 3])]).await;
+        let mut controller = mock.client().await.get_controller(0).await?;
+
+        let red = Color::new(255, 0, 0);
+        controller
+            .transaction(|buffer| buffer.colors_mut()[3..5].fill(red))
+            .await?;
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateZoneLeds, 1)
+            .await;
+        let black = Color::default();
+        assert_eq!(
+            update_zone_leds_packets(&mock),
+            vec![(1, vec![black, red, red])]
+        );
+        assert!(
+            mock.packets_of(PacketId::RGBControllerUpdateSingleLed)
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_led_zone() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2, 0, 3])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        assert_eq!(controller.zone_range(0), Some(0..2));
+        assert_eq!(controller.zone_range(1), Some(2..2));
+        assert_eq!(controller.zone_range(2), Some(2..5));
+        assert_eq!(controller.zone_range(3), None);
+        for zone_id in [0, 2] {
+            let first = controller.zone_range(zone_id).unwrap().start;
+            assert_eq!(controller.led_zone(first), Some(zone_id));
+        }
+        assert_eq!(controller.led_zone(4), Some(2));
+        assert_eq!(controller.led_zone(5), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_watch_mode() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+        let mut modes = controller.watch_mode(Duration::from_millis(5));
+
+        // another client, like the OpenRGB GUI, changes the mode
+        let other = mock.client().await.get_controller(0).await?;
+        other.set_mode_by_name("Static").await?;
+        let mode = tokio::time::timeout(Duration::from_secs(1), modes.recv())
+            .await
+            .expect("no mode change received")
+            .expect("watcher stopped");
+        assert_eq!(mode, "Static");
+
+        other.set_mode_by_name("Direct").await?;
+        let mode = tokio::time::timeout(Duration::from_secs(1), modes.recv())
+            .await
+            .expect("no mode change received")
+            .expect("watcher stopped");
+        assert_eq!(mode, "Direct");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_gamma_brightness() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2])]).await;
+        let mut controller = mock.client().await.get_controller(0).await?;
+        let colors = [Color::new(255, 128, 0), Color::new(64, 200, 10)];
+
+        controller.set_brightness(0.5);
+        controller.set_leds(colors).await?;
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 1)
+            .await;
+        assert_eq!(
+            update_leds_colors(&mock),
+            vec![Color::new(128, 64, 0), Color::new(32, 100, 5)]
+        );
+
+        controller.set_brightness(1.0);
+        controller.set_gamma(2.0);
+        controller.set_gamma(-1.0);
+        assert_eq!(controller.gamma(), 2.0);
+        controller.set_leds(colors).await?;
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 2)
+            .await;
+        assert_eq!(
+            update_leds_colors(&mock),
+            vec![Color::new(255, 64, 0), Color::new(16, 157, 0)]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_brightness_all_send_paths() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2, 2])]).await;
+        let mut controller = mock.client().await.get_controller(0).await?;
+        controller.set_brightness(0.5);
+        controller.with_history(1);
+        let color = Color::new(200, 100, 50);
+        let dimmed = Color::new(100, 50, 25);
+
+        controller.set_zone_leds(0, [color; 2]).await?;
+        controller.set_zone_colors(1, &[color; 2]).await?;
+        let mut guard = controller.begin_coalescing();
+        guard.set_led(2, color)?;
+        guard.set_led(3, color)?;
+        guard.end().await?;
+        let isolated = controller.connect_isolated().await?;
+        isolated
+            .set_zones_concurrent([(0, vec![color; 2]), (1, vec![color; 2])])
+            .await?;
+
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateZoneLeds, 5)
+            .await;
+        for packet in packets {
+            let mut msg = packet.message(5);
+            let _data_size = msg.read_u32()?;
+            let _zone_id = msg.read_u32()?;
+            assert_eq!(msg.read_value::<Vec<Color>>()?, vec![dimmed; 2]);
+        }
+
+        controller.set_leds([color; 4]).await?;
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 1)
+            .await;
+        assert_eq!(update_leds_colors(&mock), vec![dimmed; 4]);
+        // history keeps the colors as given, before brightness is applied
+        controller.with_history_frames(|h| assert_eq!(h, [vec![color; 4]]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_zones_by_size() -> OpenRgbResult<()> {
+        let controllers = vec![
+            controller_fixture("Strips", &[2, 5, 1, 3]),
+            controller_fixture("Empty", &[]),
+        ];
+        let mock = MockServer::start(5, controllers).await;
+        let client = mock.client().await;
+        let controller = client.get_controller(0).await?;
+
+        assert_eq!(controller.zones_by_size(), vec![1, 3, 0, 2]);
+        assert_eq!(controller.largest_zone(), Some(1));
+        assert_eq!(client.get_controller(1).await?.largest_zone(), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_zone_colors() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[2, 3]);
+        data.colors = (0..5).map(|i| Color::new(i, i, i)).collect();
+        let mock = MockServer::start(5, vec![data]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        assert_eq!(
+            controller.zone_colors(0)?,
+            &[Color::new(0, 0, 0), Color::new(1, 1, 1)]
+        );
+        let mut colors = controller.zone_colors(1)?.to_vec();
+        assert_eq!(
+            colors,
+            vec![
+                Color::new(2, 2, 2),
+                Color::new(3, 3, 3),
+                Color::new(4, 4, 4)
+            ]
+        );
+        assert!(controller.zone_colors(2).is_err());
+
+        colors[0] = Color::new(255, 0, 0);
+        controller.set_zone_colors(1, &colors).await?;
+        let packets = mock.wait_for_packets(2).await;
+        let mut msg = packets[1].message(5);
+        let _data_size = msg.read_u32()?;
+        assert_eq!(msg.read_u32()?, 1);
+        assert_eq!(msg.read_value::<Vec<Color>>()?, colors);
+
+        assert!(controller.set_zone_colors(1, &colors[..2]).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_all_zone_colors() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[2, 3, 1]);
+        data.colors = (0..6).map(|i| Color::new(i, i, i)).collect();
+        let mock = MockServer::start(5, vec![data]).await;
+        let mut controller = mock.client().await.get_controller(0).await?;
+
+        let gray = |i| Color::new(i, i, i);
+        assert_eq!(
+            controller.all_zone_colors().await?,
+            vec![
+                vec![gray(0), gray(1)],
+                vec![gray(2), gray(3), gray(4)],
+                vec![gray(5)]
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_all_zone_colors_short_buffer() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[2, 3]);
+        data.colors = vec![Color::new(1, 1, 1); 3];
+        let mock = MockServer::start(5, vec![data]).await;
+        let mut controller = mock.client().await.get_controller(0).await?;
+
+        let (white, black) = (Color::new(1, 1, 1), Color::default());
+        assert_eq!(
+            controller.all_zone_colors().await?,
+            vec![vec![white; 2], vec![white, black, black]]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_zone_colors_short_buffer() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[2, 3]);
+        data.colors.truncate(3);
+        let mock = MockServer::start(5, vec![data]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        assert!(controller.zone_colors(0).is_ok());
+        assert!(controller.zone_colors(1).is_err());
+        Ok(())
+    }
+
     #[tokio::test]
     #[ignore = "can only test with openrgb running"]
     async fn test_update_leds() -> OpenRgbResult<()> {
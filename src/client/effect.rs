@@ -0,0 +1,111 @@
+use std::ops::Range;
+
+use tokio::time::Instant;
+
+use crate::{Color, Controller};
+
+/// A reusable lighting effect, which renders the colors for every LED of a controller.
+///
+/// Use `Controller::render()` to render an effect and send the result to the controller.
+pub trait Effect {
+    /// Renders one frame, returning one color per LED in the controller.
+    fn render(&mut self, ctx: &EffectContext) -> Vec<Color>;
+}
+
+/// Information about the controller and frame an `Effect` is rendered for.
+#[derive(Debug, Clone)]
+pub struct EffectContext {
+    num_leds: usize,
+    zones: Vec<Range<usize>>,
+    time: Instant,
+}
+
+impl EffectContext {
+    pub(crate) fn new(controller: &Controller) -> Self {
+        let zones = controller
+            .get_all_zones()
+            .map(|zone| zone.offset()..zone.offset() + zone.num_leds())
+            .collect();
+        Self {
+            num_leds: controller.num_leds(),
+            zones,
+            time: Instant::now(),
+        }
+    }
+
+    /// Returns the number of LEDs in the controller.
+    pub fn num_leds(&self) -> usize {
+        self.num_leds
+    }
+
+    /// Returns the range of LED indices of every zone, in order of their zone ids.
+    pub fn zones(&self) -> &[Range<usize>] {
+        &self.zones
+    }
+
+    /// Returns the time at which this frame is rendered.
+    pub fn time(&self) -> Instant {
+        self.time
+    }
+}
+
+/// Effect that sets every LED to the same color.
+#[derive(Debug, Clone, Copy)]
+pub struct SolidColor(pub Color);
+
+impl Effect for SolidColor {
+    fn render(&mut self, ctx: &EffectContext) -> Vec<Color> {
+        vec![self.0; ctx.num_leds()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::protocol::mock::{MockServer, controller_fixture};
+    use crate::{Color, OpenRgbResult, PacketId};
+
+    use super::*;
+
+    /// Colors every zone in a different shade, to check the zone layout.
+    struct ZoneShades;
+
+    impl Effect for ZoneShades {
+        fn render(&mut self, ctx: &EffectContext) -> Vec<Color> {
+            let mut colors = vec![Color::default(); ctx.num_leds()];
+            for (i, zone) in ctx.zones().iter().enumerate() {
+                colors[zone.clone()].fill(Color::new(i as u8, i as u8, i as u8));
+            }
+            colors
+        }
+    }
+
+    fn update_leds_colors(packet: &crate::protocol::mock::MockPacket) -> Vec<Color> {
+        let mut msg = packet.message(5);
+        let _data_size = msg.read_u32().unwrap();
+        msg.read_value().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_render() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[1, 2])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        let red = Color::new(255, 0, 0);
+        controller.render(&mut SolidColor(red)).await?;
+        controller.render(&mut ZoneShades).await?;
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 2)
+            .await;
+
+        assert_eq!(update_leds_colors(&packets[0]), vec![red; 3]);
+        assert_eq!(
+            update_leds_colors(&packets[1]),
+            vec![
+                Color::new(0, 0, 0),
+                Color::new(1, 1, 1),
+                Color::new(1, 1, 1)
+            ]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+use std::ops::Range;
+
+use crate::{Color, Controller, OpenRgbError, OpenRgbResult};
+
+/// A buffer of LED colors that keeps track of which LEDs were changed.
+///
+/// Use this with `Controller::transaction` to only send the LEDs that actually changed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameBuffer {
+    colors: Vec<Color>,
+    original: Vec<Color>,
+}
+
+impl FrameBuffer {
+    /// Creates a new frame buffer, with `colors` as both the original and current colors.
+    pub fn new(colors: Vec<Color>) -> Self {
+        Self {
+            original: colors.clone(),
+            colors,
+        }
+    }
+
+    /// Creates a new frame buffer of black colors, with one color per LED of `controller`.
+    pub fn blank(controller: &Controller) -> Self {
+        Self::new(controller.blank_buffer())
+    }
+
+    /// Returns the number of LEDs in this buffer.
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    /// Returns true if this buffer holds no LEDs.
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    /// Returns the current colors in this buffer.
+    pub fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+
+    /// Returns the current colors in this buffer, for editing.
+    pub fn colors_mut(&mut self) -> &mut [Color] {
+        &mut self.colors
+    }
+
+    /// Returns the current color of the LED at `index`, if it exists.
+    pub fn get(&self, index: usize) -> Option<Color> {
+        self.colors.get(index).copied()
+    }
+
+    /// Sets the LED at `index` to `color`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, color: Color) -> OpenRgbResult<()> {
+        let len = self.len();
+        let led = self
+            .colors
+            .get_mut(index)
+            .ok_or(OpenRgbError::CommandError(format!(
+                "LED index {index} out of range for frame buffer of {len} LEDs"
+            )))?;
+        *led = color;
+        Ok(())
+    }
+
+    /// Sets all LEDs in this buffer to `color`.
+    pub fn fill(&mut self, color: Color) {
+        self.colors.fill(color);
+    }
+
+    /// Returns true if any LED differs from its original color.
+    pub fn is_changed(&self) -> bool {
+        self.colors != self.original
+    }
+
+    /// Returns the ranges of consecutive LEDs that differ from their original color, in ascending order.
+    pub fn changed_spans(&self) -> Vec<Range<usize>> {
+        let mut spans: Vec<Range<usize>> = Vec::new();
+        let changed = self
+            .colors
+            .iter()
+            .zip(self.original.iter())
+            .enumerate()
+            .filter(|(_, (new, old))| new != old)
+            .map(|(i, _)| i);
+        for i in changed {
+            match spans.last_mut() {
+                Some(span) if span.end == i => span.end += 1,
+                _ => spans.push(i..i + 1),
+            }
+        }
+        spans
+    }
+
+    /// Consumes this buffer, returning the current colors.
+    pub fn into_colors(self) -> Vec<Color> {
+        self.colors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::protocol::mock::{MockServer, controller_fixture};
+    use crate::{Color, FrameBuffer, OpenRgbResult};
+
+    #[test]
+    fn test_changed_spans() {
+        let mut buffer = FrameBuffer::new(vec![Color::default(); 6]);
+        assert!(!buffer.is_changed());
+        assert!(buffer.changed_spans().is_empty());
+
+        let red = Color::new(255, 0, 0);
+        buffer.set(1, red).unwrap();
+        buffer.set(2, red).unwrap();
+        buffer.colors_mut()[5] = red;
+        // setting an LED to its original color is not a change
+        buffer.set(4, Color::default()).unwrap();
+        assert!(buffer.set(6, red).is_err());
+
+        assert!(buffer.is_changed());
+        assert_eq!(buffer.changed_spans(), vec![1..3, 5..6]);
+    }
+
+    #[tokio::test]
+    async fn test_blank() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2, 3])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        assert_eq!(controller.blank_buffer(), vec![Color::default(); 5]);
+        let buffer = FrameBuffer::blank(&controller);
+        assert_eq!(buffer.len(), controller.num_leds());
+        assert!(!buffer.is_changed());
+        Ok(())
+    }
+}
@@ -1,8 +1,8 @@
 use std::collections::HashMap;
 
 use crate::{
-    Controller, OpenRgbError, OpenRgbResult, client::command::UpdateLedCommandGroup,
-    data::DeviceType,
+    Color, Controller, OpenRgbClient, OpenRgbError, OpenRgbResult,
+    client::command::UpdateLedCommandGroup, data::DeviceType,
 };
 
 /// Trait for things that can index into a `ControllerGroup`.
@@ -59,11 +59,40 @@ impl ControllerGroup {
         }
     }
 
+    /// Fetches the controllers of this group again, for when devices were added or removed since this group was created.
+    ///
+    /// Only the devices already in this group are refreshed, matched by their name, type, location and serial,
+    /// since their ids can change. Devices that were removed are dropped from the group, new devices are not added.
+    /// Refreshed controllers keep their settings, and isolated controllers (see `Controller::connect_isolated()`)
+    /// keep their connection. Connections of removed devices are closed.
+    pub async fn refresh(&mut self, client: &OpenRgbClient) -> OpenRgbResult<()> {
+        let mut fresh = client.get_all_controllers().await?.controllers;
+        let old = std::mem::take(&mut self.controllers);
+        self.controllers = old
+            .into_iter()
+            .filter_map(|controller| {
+                let i = fresh.iter().position(|c| controller.same_device(c))?;
+                Some(controller.reuse_connection(fresh.swap_remove(i)))
+            })
+            .collect();
+        Ok(())
+    }
+
     /// Returns a reference to the controllers in this group.
     pub fn controllers(&self) -> &[Controller] {
         &self.controllers
     }
 
+    /// Returns the number of controllers in this group.
+    pub fn controller_count(&self) -> usize {
+        self.controllers.len()
+    }
+
+    /// Returns the total number of LEDs of all controllers in this group.
+    pub fn total_leds(&self) -> usize {
+        self.controllers.iter().map(|c| c.num_leds()).sum()
+    }
+
     /// Splits the controllers in this group by their device type.
     /// Returns one group per device type.
     pub fn split_per_type(self) -> HashMap<DeviceType, ControllerGroup> {
@@ -78,11 +107,44 @@ impl ControllerGroup {
             })
     }
 
+    /// Splits the controllers in this group by their device type, ordered by device type.
+    ///
+    /// Unlike `split_per_type()`, the order is deterministic: groups are sorted by the numeric value of
+    /// their `DeviceType` and controllers keep their order within each group.
+    pub fn split_per_type_ordered(self) -> Vec<(DeviceType, ControllerGroup)> {
+        let mut groups = self.split_per_type().into_iter().collect::<Vec<_>>();
+        groups.sort_by_key(|(device_type, _)| u32::from(device_type));
+        groups
+    }
+
     /// Returns an iterator over the controllers in this group.
     pub fn iter(&self) -> impl Iterator<Item = &Controller> {
         self.controllers.iter()
     }
 
+    /// Sets the gamma of every controller in this group, see `Controller::set_gamma()`.
+    pub fn set_gamma(&mut self, gamma: f32) {
+        for controller in &mut self.controllers {
+            controller.set_gamma(gamma);
+        }
+    }
+
+    /// Sets the brightness factor of every controller in this group, see `Controller::set_brightness()`.
+    ///
+    /// This dims all devices uniformly, e.g. 0.5 halves the colors sent to every controller.
+    pub fn set_brightness(&mut self, factor: f32) {
+        for controller in &mut self.controllers {
+            controller.set_brightness(factor);
+        }
+    }
+
+    /// Returns an iterator over the controllers in this group whose LEDs can be set individually.
+    ///
+    /// See `Controller::is_addressable()`.
+    pub fn addressable_only(&self) -> impl Iterator<Item = &Controller> {
+        self.controllers.iter().filter(|c| c.is_addressable())
+    }
+
     /// Returns a reference to the controller with the given index.
     ///
     /// The index can be either a `usize` or a `Controller` reference.
@@ -96,7 +158,7 @@ impl ControllerGroup {
     /// Creates a new `UpdateLedCommandGroup` for this controller group.
     ///
     /// See `Controller::cmd()` for more information.
-    pub fn cmd(&self) -> UpdateLedCommandGroup {
+    pub fn cmd(&self) -> UpdateLedCommandGroup<'_> {
         UpdateLedCommandGroup::new(self)
     }
 
@@ -116,6 +178,55 @@ impl ControllerGroup {
         Ok(())
     }
 
+    /// Sets the zone with `zone_id` to `colors` on every controller in this group.
+    ///
+    /// Returns one result per controller, in order. Controllers without this zone are skipped and
+    /// report an error, without affecting the others.
+    pub async fn update_zone_all(
+        &self,
+        zone_id: usize,
+        colors: &[Color],
+    ) -> OpenRgbResult<Vec<OpenRgbResult<()>>> {
+        let mut results = Vec::with_capacity(self.controllers.len());
+        for controller in &self.controllers {
+            let result = match controller.get_zone(zone_id) {
+                Ok(_) => {
+                    controller
+                        .set_zone_leds(zone_id, colors.iter().copied())
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            results.push(result);
+        }
+        Ok(results)
+    }
+
+    /// Copies the current colors of the controller `source_id` to the controllers `target_ids`.
+    ///
+    /// The colors are fetched from the server once, then padded with black or truncated for every target,
+    /// see `Controller::mirror_to()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the controllers is not in this group, in which case nothing is sent.
+    pub async fn mirror(&self, source_id: usize, target_ids: &[usize]) -> OpenRgbResult<()> {
+        let source = self.get_controller(source_id)?;
+        let targets = target_ids
+            .iter()
+            .map(|&id| self.get_controller(id))
+            .collect::<OpenRgbResult<Vec<_>>>()?;
+        let colors = source
+            .proto()
+            .get_controller(source.id() as u32)
+            .await?
+            .colors;
+        for target in targets {
+            target.set_leds_from(colors.as_slice()).await?;
+        }
+        Ok(())
+    }
+
     /// Turns off all LEDs in all controllers in this group.
     pub async fn turn_off_leds(&self) -> OpenRgbResult<()> {
         for controller in &self.controllers {
@@ -145,10 +256,251 @@ impl<'a> IntoIterator for &'a ControllerGroup {
 
 #[cfg(test)]
 mod tests {
-    use crate::OpenRgbClient;
+    use crate::protocol::mock::{MockServer, controller_fixture};
+    use crate::{OpenRgbClient, PacketId};
 
     use super::*;
 
+    #[tokio::test]
+    async fn test_set_brightness() -> OpenRgbResult<()> {
+        let controllers = vec![
+            controller_fixture("Strip 1", &[2]),
+            controller_fixture("Strip 2", &[1]),
+        ];
+        let mock = MockServer::start(5, controllers).await;
+        let mut group = mock.client().await.get_all_controllers().await?;
+
+        group.set_brightness(0.5);
+        for controller in group.iter() {
+            controller.set_all_leds(Color::new(200, 100, 50)).await?;
+        }
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 2)
+            .await;
+        for (packet, num_leds) in packets.iter().zip([2, 1]) {
+            let mut msg = packet.message(5);
+            let _data_size = msg.read_u32()?;
+            assert_eq!(
+                msg.read_value::<Vec<Color>>()?,
+                vec![Color::new(100, 50, 25); num_leds]
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_split_per_type_ordered() -> OpenRgbResult<()> {
+        let mut keyboard = controller_fixture("Keyboard", &[1]);
+        keyboard.device_type = DeviceType::Keyboard;
+        let mut mouse = controller_fixture("Mouse", &[1]);
+        mouse.device_type = DeviceType::Mouse;
+        let mut board = controller_fixture("Board", &[1]);
+        board.device_type = DeviceType::Motherboard;
+        let controllers = vec![
+            mouse,
+            controller_fixture("Strip 1", &[1]),
+            keyboard,
+            board,
+            controller_fixture("Strip 2", &[1]),
+        ];
+        let mock = MockServer::start(5, controllers).await;
+        let client = mock.client().await;
+
+        let split = |group: ControllerGroup| {
+            group
+                .split_per_type_ordered()
+                .into_iter()
+                .map(|(device_type, group)| {
+                    let names = group.iter().map(|c| c.name().to_string()).collect();
+                    (device_type, names)
+                })
+                .collect::<Vec<(DeviceType, Vec<String>)>>()
+        };
+        let first = split(client.get_all_controllers().await?);
+        let second = split(client.get_all_controllers().await?);
+        assert_eq!(first, second);
+        assert_eq!(
+            first,
+            vec![
+                (DeviceType::Motherboard, vec!["Board".to_string()]),
+                (
+                    DeviceType::LEDStrip,
+                    vec!["Strip 1".to_string(), "Strip 2".to_string()]
+                ),
+                (DeviceType::Keyboard, vec!["Keyboard".to_string()]),
+                (DeviceType::Mouse, vec!["Mouse".to_string()]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_addressable_only() -> OpenRgbResult<()> {
+        let mut fixed = controller_fixture("Fixed", &[4]);
+        fixed.modes.remove(0);
+        let controllers = vec![
+            controller_fixture("Strip", &[2]),
+            fixed,
+            controller_fixture("Empty", &[]),
+            controller_fixture("Keyboard", &[10, 5]),
+        ];
+        let mock = MockServer::start(5, controllers).await;
+        let group = mock.client().await.get_all_controllers().await?;
+
+        let names = group
+            .addressable_only()
+            .map(|c| c.name())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["Strip", "Keyboard"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_total_leds() -> OpenRgbResult<()> {
+        let controllers = vec![
+            controller_fixture("Keyboard", &[10, 5]),
+            controller_fixture("Strip", &[30]),
+            controller_fixture("Empty", &[]),
+        ];
+        let mock = MockServer::start(5, controllers).await;
+        let group = mock.client().await.get_all_controllers().await?;
+
+        assert_eq!(group.controller_count(), 3);
+        assert_eq!(group.total_leds(), 45);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mirror() -> OpenRgbResult<()> {
+        let mut source = controller_fixture("Ram 1", &[2]);
+        let colors = vec![Color::new(1, 2, 3), Color::new(4, 5, 6)];
+        source.colors = colors.clone();
+        let controllers = vec![
+            source,
+            controller_fixture("Ram 2", &[2]),
+            controller_fixture("Strip", &[3]),
+        ];
+        let mock = MockServer::start(5, controllers).await;
+        let group = mock.client().await.get_all_controllers().await?;
+
+        assert!(group.mirror(0, &[1, 5]).await.is_err());
+        group.mirror(0, &[1, 2]).await?;
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 2)
+            .await;
+        let sent = packets
+            .iter()
+            .map(|p| {
+                let mut msg = p.message(5);
+                let _data_size = msg.read_u32()?;
+                Ok((p.device_id, msg.read_value::<Vec<Color>>()?))
+            })
+            .collect::<OpenRgbResult<Vec<_>>>()?;
+        assert_eq!(
+            sent,
+            vec![
+                (1, colors.clone()),
+                (2, vec![colors[0], colors[1], Color::default()]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_refresh() -> OpenRgbResult<()> {
+        let controllers = vec![
+            controller_fixture("Keyboard", &[3]),
+            controller_fixture("Strip", &[4]),
+            controller_fixture("Fan", &[2]),
+        ];
+        let mock = MockServer::start(5, controllers).await;
+        let client = mock.client().await;
+        let mut group = client.get_all_controllers().await?;
+        for i in [1, 2] {
+            group.controllers[i] = group.controllers[i].connect_isolated().await?;
+        }
+        mock.wait_for_open_connections(3).await;
+
+        // the keyboard was unplugged and a mouse added, which is not part of the group
+        mock.set_controllers(vec![
+            controller_fixture("Mouse", &[1]),
+            controller_fixture("Strip", &[4]),
+            controller_fixture("Fan", &[2]),
+        ]);
+        group.refresh(&client).await?;
+
+        let names = group.iter().map(|c| c.name()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Strip", "Fan"]);
+        let ids = group.iter().map(|c| c.id()).collect::<Vec<_>>();
+        assert_eq!(ids, vec![1, 2]);
+        let isolated = group.iter().map(|c| c.is_isolated()).collect::<Vec<_>>();
+        assert_eq!(isolated, vec![true, true]);
+        assert_eq!(mock.total_connections(), 3);
+
+        // the fan was unplugged, so its connection is closed
+        mock.set_controllers(vec![controller_fixture("Strip", &[4])]);
+        group.refresh(&client).await?;
+        assert_eq!(group.controllers().len(), 1);
+        mock.wait_for_open_connections(2).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_refresh_filtered() -> OpenRgbResult<()> {
+        let fixture = |name, device_type, leds| {
+            let mut data = controller_fixture(name, &[leds]);
+            data.device_type = device_type;
+            data
+        };
+        let keyboard = || fixture("Keyboard", DeviceType::Keyboard, 3);
+        let mock = MockServer::start(
+            5,
+            vec![keyboard(), fixture("Strip", DeviceType::LEDStrip, 4)],
+        )
+        .await;
+        let client = mock.client().await;
+        let mut strips = client.get_controllers_of_type(DeviceType::LEDStrip).await?;
+        strips.controllers[0].set_brightness(0.5);
+
+        // the strip moved to id 0 and got resized
+        mock.set_controllers(vec![fixture("Strip", DeviceType::LEDStrip, 5), keyboard()]);
+        strips.refresh(&client).await?;
+
+        assert_eq!(strips.controller_count(), 1);
+        let refreshed = &strips.controllers()[0];
+        assert_eq!((refreshed.name(), refreshed.id()), ("Strip", 0));
+        assert_eq!(refreshed.data().zones[0].leds_count, 5);
+        assert_eq!(refreshed.brightness(), 0.5);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_update_zone_all() -> OpenRgbResult<()> {
+        let controllers = vec![
+            controller_fixture("Keyboard", &[3, 2]),
+            controller_fixture("Strip", &[4]),
+            controller_fixture("Fan", &[2, 2]),
+        ];
+        let mock = MockServer::start(5, controllers).await;
+        let group = mock.client().await.get_all_controllers().await?;
+
+        let results = group
+            .update_zone_all(1, &[Color::new(255, 0, 0); 2])
+            .await?;
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert!(results[2].is_ok());
+
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateZoneLeds, 2)
+            .await;
+        let mut devices = packets.iter().map(|p| p.device_id).collect::<Vec<_>>();
+        devices.sort();
+        assert_eq!(devices, vec![0, 2]);
+        Ok(())
+    }
+
     #[tokio::test]
     #[ignore = "can only test with openrgb running"]
     async fn test_group() -> OpenRgbResult<()> {
@@ -1,20 +1,39 @@
 //! Wrapper around the OpenRGB client to make it friendlier to use.
 
+mod batch;
+mod coalesce;
 mod command;
 mod controller;
+mod effect;
+mod framebuffer;
 mod group;
+mod multi;
+mod notification;
+pub mod patterns;
+mod plugin;
+mod pool;
 mod segment;
+mod source;
+#[cfg(feature = "serde")]
+mod state;
+mod tree;
 mod zone;
 
-pub use {command::*, controller::*, group::*, segment::*, zone::*};
+pub use {
+    batch::*, coalesce::*, command::*, controller::*, effect::*, framebuffer::*, group::*,
+    multi::*, notification::*, plugin::*, pool::*, segment::*, source::*, tree::*, zone::*,
+};
+
+use std::time::{Duration, Instant};
 
-use tokio::net::ToSocketAddrs;
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tracing::Instrument;
 
 use crate::{
-    DEFAULT_PROTOCOL, OpenRgbError, PluginData,
+    Color, DEFAULT_PROTOCOL, OpenRgbError, PluginData,
     data::DeviceType,
     error::OpenRgbResult,
-    protocol::{DEFAULT_ADDR, OpenRgbProtocol},
+    protocol::{DEFAULT_ADDR, OpenRgbProtocol, ProtocolStream},
 };
 
 /// Client for the OpenRGB SDK server that provides methods to interact with OpenRGB.
@@ -26,22 +45,28 @@ use crate::{
 /// # Example
 pub struct OpenRgbClient {
     proto: OpenRgbProtocol,
+    /// Device type of every controller by id, used to only fetch controllers of a requested type.
+    ///
+    /// Built by the first type query, see `refresh_index()`.
+    device_index: std::sync::Mutex<Option<Vec<DeviceType>>>,
+    /// Controller count the server reported last, used by `get_controller()` to fail early.
+    controller_count: std::sync::Mutex<Option<usize>>,
 }
 
 impl OpenRgbClient {
     /// Connect to default OpenRGB server.
     ///
-    /// Use [OpenRGB::connect_to] to connect to a specific server.
+    /// Use [OpenRgbClient::connect_to] to connect to a specific server.
     ///
     /// # Example
     ///
     /// ```no_run
-    /// # use openrgb::OpenRGB;
+    /// # use openrgb::OpenRgbClient;
     /// # use std::error::Error;
     /// #
     /// # #[tokio::main]
     /// # async fn main() -> Result<(), Box<dyn Error>> {
-    /// let client = OpenRGB::connect().await?;
+    /// let client = OpenRgbClient::connect().await?;
     /// #
     /// # Ok(())
     /// # }
@@ -52,19 +77,19 @@ impl OpenRgbClient {
 
     /// Connect to OpenRGB server at given coordinates.
     ///
-    /// Use [OpenRGB::connect] to connect to default server.
+    /// Use [OpenRgbClient::connect] to connect to default server.
     ///
     /// # Arguments
     /// * `addr` - A socket address (eg: a `(host, port)` tuple)
     ///
     /// # Example
     /// ```no_run
-    /// # use openrgb::OpenRGB;
+    /// # use openrgb::OpenRgbClient;
     /// # use std::error::Error;
     /// #
     /// # #[tokio::main]
     /// # async fn main() -> Result<(), Box<dyn Error>> {
-    /// let client = OpenRGB::connect_to(("localhost", 6742)).await?;
+    /// let client = OpenRgbClient::connect_to(("localhost", 6742), 5).await?;
     /// #
     /// # Ok(())
     /// # }
@@ -74,7 +99,27 @@ impl OpenRgbClient {
         protocol_version: u32,
     ) -> OpenRgbResult<Self> {
         let client = OpenRgbProtocol::connect_to(addr, protocol_version).await?;
-        Ok(Self { proto: client })
+        Ok(Self::new(client))
+    }
+
+    /// Creates a client from an already connected `stream`, skipping the protocol version handshake.
+    ///
+    /// This is meant for connections where the handshake was done out-of-band, such as a stream handed over by a test harness.
+    /// `protocol_version` must be the version that was negotiated with the server, otherwise packets will be misinterpreted.
+    pub fn from_connected_stream(stream: TcpStream, protocol_version: u32) -> Self {
+        let stream = ProtocolStream::from_stream(stream, protocol_version);
+        Self::new(OpenRgbProtocol::from_connected_stream(
+            stream,
+            protocol_version,
+        ))
+    }
+
+    pub(crate) fn new(proto: OpenRgbProtocol) -> Self {
+        Self {
+            proto,
+            device_index: std::sync::Mutex::new(None),
+            controller_count: std::sync::Mutex::new(None),
+        }
     }
 }
 
@@ -85,15 +130,83 @@ impl OpenRgbClient {
     ///
     /// This function returns an error if communication with the OpenRGB SDK server fails.
     pub async fn get_all_controllers(&self) -> OpenRgbResult<ControllerGroup> {
-        let count = self.proto.get_controller_count().await? as usize;
-        let mut controllers = Vec::with_capacity(count as usize);
+        let count = self.fetch_controller_count().await?;
+        let mut controllers = Vec::with_capacity(count);
         for id in 0..count {
-            let controller = self.get_controller(id).await?;
-            controllers.push(controller);
+            let c_data = self.proto.get_controller(id as u32).await?;
+            controllers.push(Controller::new(id, self.proto.clone(), c_data));
         }
         Ok(ControllerGroup::new(controllers))
     }
 
+    /// Returns all controllers whose data could be read, and the errors for the controllers whose data could not.
+    ///
+    /// Unlike `get_all_controllers()`, this does not fail if a single controller sends data that cannot be parsed,
+    /// so the other controllers stay usable. Failures are returned as `(controller id, error)`.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the controller count could not be read.
+    pub async fn get_all_controllers_lenient(
+        &self,
+    ) -> OpenRgbResult<(ControllerGroup, Vec<(usize, OpenRgbError)>)> {
+        let count = self.fetch_controller_count().await?;
+        let mut controllers = Vec::with_capacity(count);
+        let mut failures = Vec::new();
+        for id in 0..count {
+            match self.proto.get_controller(id as u32).await {
+                Ok(c_data) => controllers.push(Controller::new(id, self.proto.clone(), c_data)),
+                Err(e) => {
+                    tracing::warn!("Failed reading data of controller {id}: {e}");
+                    failures.push((id, e));
+                }
+            }
+        }
+        Ok((ControllerGroup::new(controllers), failures))
+    }
+
+    /// Returns a snapshot of all controllers with their zones and segments, for tree views or debugging.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if communication with the OpenRGB SDK server fails.
+    pub async fn device_tree(&self) -> OpenRgbResult<Vec<DeviceNode>> {
+        let group = self.get_all_controllers().await?;
+        Ok(group.iter().map(DeviceNode::new).collect())
+    }
+
+    /// Exports the active mode, mode settings and colors of every controller as JSON, for backups.
+    ///
+    /// Unlike profiles, which are stored on the server, the returned document can be inspected and stored anywhere.
+    /// Use `import_state()` to restore it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if communication with the OpenRGB SDK server fails.
+    #[cfg(feature = "serde")]
+    pub async fn export_state(&self) -> OpenRgbResult<String> {
+        let group = self.get_all_controllers().await?;
+        state::ClientState::new(group.controllers())?.to_json()
+    }
+
+    /// Restores a state exported with `export_state()`.
+    ///
+    /// Controllers are matched by name, location, serial and device type, so they are found even if their ids changed.
+    /// Returns the result of every controller in `json` by name, a controller that is no longer present fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `json` is not a valid state, or communication with the OpenRGB SDK server fails.
+    #[cfg(feature = "serde")]
+    pub async fn import_state(
+        &self,
+        json: &str,
+    ) -> OpenRgbResult<Vec<(String, OpenRgbResult<()>)>> {
+        let state = state::ClientState::from_json(json)?;
+        let group = self.get_all_controllers().await?;
+        Ok(state.apply(group.into_iter().collect()).await)
+    }
+
     /// Returns all controllers of a specific type.
     ///
     /// Use `ControllerGrou::split_per_type` to get all controllers per type.
@@ -105,37 +218,305 @@ impl OpenRgbClient {
         &self,
         device_type: DeviceType,
     ) -> OpenRgbResult<ControllerGroup> {
-        let group = self.get_all_controllers().await?;
-        group
-            .split_per_type()
-            .remove(&device_type)
-            .ok_or(OpenRgbError::CommandError(format!(
+        let count = self.fetch_controller_count().await?;
+        let index = self
+            .device_index
+            .lock()
+            .expect("Device index poisoned")
+            .clone()
+            .filter(|index| index.len() == count);
+
+        let controllers = match index {
+            Some(index) => {
+                let mut controllers = Vec::new();
+                for id in (0..count).filter(|id| index[*id] == device_type) {
+                    let c_data = self.proto.get_controller(id as u32).await?;
+                    controllers.push(Controller::new(id, self.proto.clone(), c_data));
+                }
+                if controllers
+                    .iter()
+                    .all(|c| c.data().device_type == device_type)
+                {
+                    controllers
+                } else {
+                    // the device list changed since the index was built
+                    self.fetch_all_indexed(device_type).await?
+                }
+            }
+            None => self.fetch_all_indexed(device_type).await?,
+        };
+        if controllers.is_empty() {
+            return Err(OpenRgbError::CommandError(format!(
                 "No controllers of type {device_type:?} found"
-            )))
+            )));
+        }
+        Ok(ControllerGroup::new(controllers))
     }
 
-    /// Gets a controller by its index.
+    /// Rebuilds the device type index used by `get_controllers_of_type()`.
+    ///
+    /// The index is rebuilt automatically when the number of controllers changes,
+    /// call this after devices were replaced without changing their number.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if communication with the OpenRGB SDK server fails.
+    pub async fn refresh_index(&self) -> OpenRgbResult<()> {
+        let group = self.get_all_controllers().await?;
+        self.store_index(&group);
+        Ok(())
+    }
+
+    /// Fetches all controllers, rebuilding the device index, and returns those of `device_type`.
+    async fn fetch_all_indexed(&self, device_type: DeviceType) -> OpenRgbResult<Vec<Controller>> {
+        let group = self.get_all_controllers().await?;
+        self.store_index(&group);
+        Ok(group
+            .into_iter()
+            .filter(|c| c.data().device_type == device_type)
+            .collect())
+    }
+
+    fn store_index(&self, group: &ControllerGroup) {
+        let index = group.iter().map(|c| c.data().device_type).collect();
+        *self.device_index.lock().expect("Device index poisoned") = Some(index);
+    }
+
+    /// Returns the names of the modes of every controller, paired with the controller's id.
+    ///
+    /// This fetches all controllers, see `get_all_controllers`.
     ///
     /// # Errors
     ///
     /// This function returns an error if communication with the OpenRGB SDK server fails.
+    pub async fn modes_per_controller(&self) -> OpenRgbResult<Vec<(usize, Vec<String>)>> {
+        let group = self.get_all_controllers().await?;
+        let modes = group
+            .iter()
+            .map(|c| {
+                let names = c.data().modes.iter().map(|m| m.name.clone()).collect();
+                (c.id(), names)
+            })
+            .collect();
+        Ok(modes)
+    }
+
+    /// Sets every addressable controller to a controllable mode and all of its LEDs to `color`.
+    ///
+    /// Controllers that are not addressable (see `Controller::is_addressable()`) are skipped.
+    /// Returns the result for each addressable controller in order of their ids,
+    /// a failure for one controller does not stop the others.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the controllers could not be fetched.
+    pub async fn set_everything(&self, color: Color) -> OpenRgbResult<Vec<OpenRgbResult<()>>> {
+        let group = self.get_all_controllers().await?;
+        let mut results = Vec::new();
+        for controller in group.addressable_only() {
+            let result = async {
+                controller.set_controllable_mode().await?;
+                controller.set_all_leds(color).await
+            }
+            .await;
+            results.push(result);
+        }
+        Ok(results)
+    }
+
+    /// Runs `f` on every controller concurrently, returning the result for each controller in order of their ids.
+    ///
+    /// A failure of `f` for one controller does not stop it from running on the others.
+    /// All controllers share this client's connection, use `for_each_controller_isolated` to give every controller its own.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the controllers could not be fetched.
+    pub async fn for_each_controller<F, Fut>(&self, f: F) -> OpenRgbResult<Vec<OpenRgbResult<()>>>
+    where
+        F: Fn(Controller) -> Fut,
+        Fut: Future<Output = OpenRgbResult<()>> + Send + 'static,
+    {
+        let group = self.get_all_controllers().await?;
+        Ok(Self::run_for_each(group.into_iter().map(f)).await)
+    }
+
+    /// Same as `for_each_controller`, but every controller gets its own connection, see `Controller::connect_isolated`.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the controllers could not be fetched or connected.
+    pub async fn for_each_controller_isolated<F, Fut>(
+        &self,
+        f: F,
+    ) -> OpenRgbResult<Vec<OpenRgbResult<()>>>
+    where
+        F: Fn(Controller) -> Fut,
+        Fut: Future<Output = OpenRgbResult<()>> + Send + 'static,
+    {
+        let group = self.get_all_controllers().await?;
+        let mut futures = Vec::with_capacity(group.controllers().len());
+        for controller in &group {
+            futures.push(f(controller.connect_isolated().await?));
+        }
+        Ok(Self::run_for_each(futures).await)
+    }
+
+    async fn run_for_each<Fut>(futures: impl IntoIterator<Item = Fut>) -> Vec<OpenRgbResult<()>>
+    where
+        Fut: Future<Output = OpenRgbResult<()>> + Send + 'static,
+    {
+        let handles = futures.into_iter().map(tokio::spawn).collect::<Vec<_>>();
+        let mut results = Vec::with_capacity(handles.len());
+        for handle in handles {
+            let result = handle.await.unwrap_or_else(|e| {
+                Err(OpenRgbError::CommandError(format!(
+                    "Controller task failed: {e}"
+                )))
+            });
+            results.push(result);
+        }
+        results
+    }
+
+    /// Gets a controller by its index.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if communication with the OpenRGB SDK server fails,
+    /// or if the controller count last reported by the server shows that no controller with index `i` exists,
+    /// for example when the server has not detected any devices.
+    /// The count is cached by requests like `get_controller_count()` and `get_all_controllers()`,
+    /// so call one of those after a rescan.
     pub async fn get_controller(&self, i: usize) -> OpenRgbResult<Controller> {
-        let c_data = self.proto.get_controller(i as u32).await?;
-        Ok(Controller::new(i, self.proto.clone(), c_data))
+        let span = tracing::info_span!("get_controller", controller_id = i);
+        traced(span, async {
+            // the server does not respond to requests for non-existent controllers
+            let cached = *self
+                .controller_count
+                .lock()
+                .expect("Controller count poisoned");
+            if let Some(count) = cached {
+                self.check_controller_index(i, count)?;
+            }
+            let c_data = self.proto.get_controller(i as u32).await?;
+            Ok(Controller::new(i, self.proto.clone(), c_data))
+        })
+        .await
+    }
+
+    /// Returns an error if controller `i` does not exist on a server with `count` controllers.
+    fn check_controller_index(&self, i: usize, count: usize) -> OpenRgbResult<()> {
+        if count == 0 {
+            return Err(OpenRgbError::CommandError(
+                "No controllers present, the OpenRGB server has not detected any devices"
+                    .to_string(),
+            ));
+        }
+        if i >= count {
+            return Err(OpenRgbError::CommandError(format!(
+                "Controller {i} not found, there are only {count} controllers"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the controller with the given id, after switching it to a controllable mode.
+    ///
+    /// This changes the mode of the device, see `Controller::set_controllable_mode()`.
+    /// The controller data is synced afterwards, so the returned controller reports the new mode.
+    pub async fn get_controller_ready(&self, i: usize) -> OpenRgbResult<Controller> {
+        let mut controller = self.get_controller(i).await?;
+        controller.set_controllable_mode().await?;
+        controller.sync_controller_data().await?;
+        Ok(controller)
     }
 }
 
+/// Runs `fut` inside `span`, logging a warning in the span if it fails.
+///
+/// This lets users filtering logs see which device an error pertains to.
+pub(crate) async fn traced<T>(
+    span: tracing::Span,
+    fut: impl Future<Output = OpenRgbResult<T>>,
+) -> OpenRgbResult<T> {
+    async {
+        let res = fut.await;
+        if let Err(e) = &res {
+            tracing::warn!("Operation failed: {e}");
+        }
+        res
+    }
+    .instrument(span)
+    .await
+}
+
 // delegation if it would exist
 impl OpenRgbClient {
     /// Returns the protocol version for this client.
-    pub fn get_protocol_version(&mut self) -> u32 {
+    pub fn get_protocol_version(&self) -> u32 {
         self.proto.get_protocol_version()
     }
 
+    /// Returns the protocol version negotiated with the server, which is the same as `get_protocol_version()`.
+    ///
+    /// This is the lowest of `server_protocol_version()` and the highest version supported by this crate.
+    pub fn negotiated_protocol_version(&self) -> u32 {
+        self.proto.get_protocol_version()
+    }
+
+    /// Returns the highest protocol version the server reported it supports.
+    ///
+    /// If this is higher than `negotiated_protocol_version()`, features are limited by this crate rather than the server.
+    /// For clients created with `from_connected_stream()` the server version is unknown, and this returns the negotiated version.
+    pub fn server_protocol_version(&self) -> u32 {
+        self.proto.get_server_protocol_version()
+    }
+
+    /// Checks that the negotiated protocol version is at least `min`.
+    ///
+    /// Useful to gate custom extensions the same way the built-in requests are gated.
+    ///
+    /// # Errors
+    ///
+    /// Returns `OpenRgbError::UnsupportedOperation` for `op` if the protocol version is lower than `min`.
+    pub fn require_version(&self, min: u32, op: &str) -> OpenRgbResult<()> {
+        self.proto.check_protocol_version(min, op)
+    }
+
+    /// Returns a best-effort description of the server version, for use in bug reports.
+    ///
+    /// The SDK does not report the OpenRGB version of the server,
+    /// so this is derived from the negotiated protocol version using the following table:
+    ///
+    /// | Protocol | OpenRGB  |
+    /// |----------|----------|
+    /// | 0        | 0.5      |
+    /// | 1        | 0.6      |
+    /// | 2        | 0.6      |
+    /// | 3        | 0.7, 0.8 |
+    /// | 4        | 0.9      |
+    /// | 5        | 1.0rc    |
+    ///
+    /// Since the negotiated version is capped at the highest version this client supports, the server may be newer than described.
+    /// Returns `None` for unknown protocol versions.
+    pub fn server_version(&self) -> Option<String> {
+        let version = self.get_protocol_version();
+        let openrgb = match version {
+            0 => "0.5",
+            1 | 2 => "0.6",
+            3 => "0.7",
+            4 => "0.9",
+            5 => "1.0rc",
+            _ => return None,
+        };
+        Some(format!("protocol {version} (OpenRGB >= {openrgb})"))
+    }
+
     /// Sets the name for this client's connection.
     ///
     /// This is viewable in the OpenRGB SDK server tab
-    pub async fn set_name(&mut self, name: impl Into<String>) -> OpenRgbResult<()> {
+    pub async fn set_name(&self, name: impl Into<String>) -> OpenRgbResult<()> {
         self.proto.set_name(name).await
     }
 
@@ -149,6 +530,19 @@ impl OpenRgbClient {
         self.proto.save_profile(name).await
     }
 
+    /// Saves the current state as a profile with the given name, replacing an existing profile with that name.
+    ///
+    /// The server snapshots its own live state of all devices, so pending changes held by this client
+    /// (e.g. an unexecuted `UpdateLedCommand`) are not included.
+    /// If a profile with this name exists, it is deleted first, so re-saving does not create duplicates.
+    pub async fn save_current_as_profile(&self, name: impl Into<String>) -> OpenRgbResult<()> {
+        let name = name.into();
+        if self.proto.get_profiles().await?.contains(&name) {
+            self.proto.delete_profile(name.as_str()).await?;
+        }
+        self.proto.save_profile(name).await
+    }
+
     /// Load the profile with the given name.
     pub async fn load_profile(&self, name: impl Into<String>) -> OpenRgbResult<()> {
         self.proto.load_profile(name).await
@@ -160,8 +554,31 @@ impl OpenRgbClient {
     }
 
     /// Returns the number of controllers connected to OpenRGB.
-    pub async fn get_controller_count(&mut self) -> OpenRgbResult<u32> {
-        self.proto.get_controller_count().await
+    pub async fn get_controller_count(&self) -> OpenRgbResult<u32> {
+        Ok(self.fetch_controller_count().await? as u32)
+    }
+
+    /// Requests the controller count and caches it for `get_controller()`.
+    async fn fetch_controller_count(&self) -> OpenRgbResult<usize> {
+        let count = self.proto.get_controller_count().await? as usize;
+        *self
+            .controller_count
+            .lock()
+            .expect("Controller count poisoned") = Some(count);
+        Ok(count)
+    }
+
+    /// Checks that the server still responds and returns the round-trip time.
+    ///
+    /// The protocol has no dedicated ping, so this does a real request for the controller count.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection is closed or the server does not answer.
+    pub async fn ping(&self) -> OpenRgbResult<Duration> {
+        let start = Instant::now();
+        self.fetch_controller_count().await?;
+        Ok(start.elapsed())
     }
 
     /// Returns a list of available plugins installed on OpenRGB.
@@ -169,8 +586,384 @@ impl OpenRgbClient {
         self.proto.get_plugins().await
     }
 
+    /// Starts listening for notifications of the server, like `DeviceListUpdated`, on a new connection.
+    ///
+    /// The reader reconnects with the default `ReconnectPolicy` when its connection is lost.
+    pub async fn notification_reader(&self) -> OpenRgbResult<NotificationReader> {
+        self.notification_reader_with(ReconnectPolicy::default())
+            .await
+    }
+
+    /// Starts listening for notifications of the server on a new connection, reconnecting using `policy`.
+    pub async fn notification_reader_with(
+        &self,
+        policy: ReconnectPolicy,
+    ) -> OpenRgbResult<NotificationReader> {
+        let proto = self.proto.connect_clone().await?;
+        NotificationReader::start(proto, policy).await
+    }
+
+    /// Returns the installed plugin with the given `name`, ignoring case.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no plugin with this name is installed.
+    pub async fn find_plugin(&self, name: &str) -> OpenRgbResult<Plugin> {
+        let data = plugin::find_plugin_data(&self.proto, name).await?;
+        Ok(Plugin::new(self.proto.clone(), data))
+    }
+
     /// Forces the OpenRGB instance to rescan for devices.
     pub async fn rescan_devices(&self) -> OpenRgbResult<()> {
         self.proto.rescan_devices().await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::net::TcpStream;
+    use tracing_test::traced_test;
+
+    use super::OpenRgbClient;
+    use crate::data::{DeviceType, ModeFlag};
+    use crate::protocol::mock::{MockServer, controller_fixture, mode_fixture};
+    use crate::{Color, OpenRgbError, OpenRgbResult, PacketId};
+
+    #[tokio::test]
+    #[traced_test]
+    async fn test_get_controller_span() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[1])]).await;
+        let client = mock.client().await;
+        client.get_controller_count().await?;
+
+        assert!(client.get_controller(3).await.is_err());
+        assert!(logs_contain("get_controller{controller_id=3}"));
+        assert!(logs_contain("Controller 3 not found"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_controllers_of_type_index() -> OpenRgbResult<()> {
+        let mut keyboard = controller_fixture("Keyboard", &[4]);
+        keyboard.device_type = DeviceType::Keyboard;
+        let mut strip = controller_fixture("Strip", &[2]);
+        strip.device_type = DeviceType::LEDStrip;
+        let mut other_strip = controller_fixture("Other Strip", &[2]);
+        other_strip.device_type = DeviceType::LEDStrip;
+        let mock = MockServer::start(5, vec![keyboard, strip, other_strip]).await;
+        let client = mock.client().await;
+
+        let requested = |id: u32| {
+            mock.packets_of(PacketId::RequestControllerData)
+                .iter()
+                .filter(|p| p.device_id == id)
+                .count()
+        };
+
+        let strips = client.get_controllers_of_type(DeviceType::LEDStrip).await?;
+        assert_eq!(strips.controllers().len(), 2);
+        let strips = client.get_controllers_of_type(DeviceType::LEDStrip).await?;
+        assert_eq!(strips.controllers().len(), 2);
+        // the keyboard is only fetched while building the index
+        assert_eq!(requested(0), 1);
+        assert_eq!(requested(1), 2);
+        assert!(
+            client
+                .get_controllers_of_type(DeviceType::Mouse)
+                .await
+                .is_err()
+        );
+        assert_eq!(requested(0), 1);
+
+        client.refresh_index().await?;
+        assert_eq!(requested(0), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_server_version() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![]).await;
+        let client = mock.client().await;
+        assert_eq!(
+            client.server_version().as_deref(),
+            Some("protocol 5 (OpenRGB >= 1.0rc)")
+        );
+
+        let mock = MockServer::start(4, vec![]).await;
+        let client = mock.client().await;
+        assert_eq!(
+            client.server_version().as_deref(),
+            Some("protocol 4 (OpenRGB >= 0.9)")
+        );
+
+        let mock = MockServer::start(3, vec![]).await;
+        let client = mock.client().await;
+        assert_eq!(
+            client.server_version().as_deref(),
+            Some("protocol 3 (OpenRGB >= 0.7)")
+        );
+
+        let mock = MockServer::start(2, vec![]).await;
+        let client = mock.client().await;
+        assert_eq!(
+            client.server_version().as_deref(),
+            Some("protocol 2 (OpenRGB >= 0.6)")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_modes_per_controller() -> OpenRgbResult<()> {
+        let first = controller_fixture("First", &[4]);
+        let mut second = controller_fixture("Second", &[2]);
+        second
+            .modes
+            .push(mode_fixture("Rainbow", ModeFlag::HasSpeed));
+        let mock = MockServer::start(5, vec![first, second]).await;
+        let client = mock.client().await;
+
+        let modes = client.modes_per_controller().await?;
+        assert_eq!(
+            modes,
+            vec![
+                (0, vec!["Direct".to_string(), "Static".to_string()]),
+                (
+                    1,
+                    vec![
+                        "Direct".to_string(),
+                        "Static".to_string(),
+                        "Rainbow".to_string()
+                    ]
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shared_client() -> OpenRgbResult<()> {
+        let controllers = vec![
+            controller_fixture("First", &[1]),
+            controller_fixture("Second", &[1]),
+        ];
+        let mock = MockServer::start(5, controllers).await;
+        let client = Arc::new(mock.client().await);
+
+        let tasks = (0..4)
+            .map(|i| {
+                let client = Arc::clone(&client);
+                tokio::spawn(async move {
+                    client.set_name(format!("Task {i}")).await?;
+                    assert_eq!(client.get_protocol_version(), 5);
+                    client.get_controller_count().await
+                })
+            })
+            .collect::<Vec<_>>();
+        for task in tasks {
+            assert_eq!(task.await.unwrap()?, 2);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_from_connected_stream() -> OpenRgbResult<()> {
+        let mock = MockServer::start(4, vec![controller_fixture("Strip", &[1])]).await;
+        let stream = TcpStream::connect(mock.addr()).await?;
+        let client = OpenRgbClient::from_connected_stream(stream, 4);
+
+        assert_eq!(client.get_protocol_version(), 4);
+        assert_eq!(client.get_controller_count().await?, 1);
+        assert!(
+            mock.all_packets()
+                .iter()
+                .all(|p| p.packet_id != PacketId::RequestProtocolVersion)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_for_each_controller() -> OpenRgbResult<()> {
+        let controllers = (0..3)
+            .map(|i| controller_fixture(&format!("Strip {i}"), &[2]))
+            .collect();
+        let mock = MockServer::start(5, controllers).await;
+        let client = mock.client().await;
+
+        let results = client
+            .for_each_controller(|c| async move {
+                if c.id() == 1 {
+                    return Err(OpenRgbError::CommandError("failed".to_string()));
+                }
+                c.turn_off_leds().await
+            })
+            .await?;
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        assert!(results[2].is_ok());
+
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 2)
+            .await;
+        let mut devices = packets.iter().map(|p| p.device_id).collect::<Vec<_>>();
+        devices.sort();
+        assert_eq!(devices, vec![0, 2]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_server_protocol_version() -> OpenRgbResult<()> {
+        let mock = MockServer::start(6, vec![]).await;
+        let client = mock.client().await;
+        assert_eq!(client.server_protocol_version(), 6);
+        assert_eq!(client.negotiated_protocol_version(), 5);
+
+        let mock = MockServer::start(3, vec![]).await;
+        let client = mock.client().await;
+        assert_eq!(client.server_protocol_version(), 3);
+        assert_eq!(client.negotiated_protocol_version(), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_require_version() -> OpenRgbResult<()> {
+        let mock = MockServer::start(3, vec![controller_fixture("Strip", &[1])]).await;
+        let client = mock.client().await;
+
+        let err = client.require_version(4, "Custom op").unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                OpenRgbError::UnsupportedOperation {
+                    operation,
+                    current_protocol_version: 3,
+                    min_protocol_version: 4,
+                } if operation == "Custom op"
+            ),
+            "{err}"
+        );
+        client.require_version(3, "Custom op")?;
+        client.require_version(2, "Custom op")?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_all_controllers_lenient() -> OpenRgbResult<()> {
+        let controllers = ["A", "B", "C", "D"]
+            .iter()
+            .map(|name| controller_fixture(name, &[2]))
+            .collect();
+        let mock = MockServer::start(5, controllers).await;
+        mock.set_malformed_controller(2);
+        let client = mock.client().await;
+
+        assert!(client.get_all_controllers().await.is_err());
+        let (group, failures) = client.get_all_controllers_lenient().await?;
+        let names = group.iter().map(|c| c.name()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["A", "B", "D"]);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_controller_ready() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[2]);
+        data.active_mode = 1;
+        let mock = MockServer::start(5, vec![data]).await;
+        let client = mock.client().await;
+
+        let controller = client.get_controller_ready(0).await?;
+        let mode = controller.active_mode()?;
+        assert!(
+            mode.flags.contains(ModeFlag::HasPerLEDColor),
+            "{}",
+            mode.name
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_everything() -> OpenRgbResult<()> {
+        let mut fixed = controller_fixture("Fixed", &[4]);
+        fixed.modes.remove(0);
+        let controllers = vec![
+            controller_fixture("Strip", &[2]),
+            fixed,
+            controller_fixture("Keyboard", &[3]),
+        ];
+        let mock = MockServer::start(5, controllers).await;
+        let client = mock.client().await;
+        let red = Color::new(255, 0, 0);
+
+        let results = client.set_everything(red).await?;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.is_ok()));
+
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 2)
+            .await;
+        let sent = packets
+            .iter()
+            .map(|p| {
+                let mut msg = p.message(5);
+                let _data_size = msg.read_u32()?;
+                Ok((p.device_id, msg.read_value::<Vec<Color>>()?))
+            })
+            .collect::<OpenRgbResult<Vec<_>>>()?;
+        assert_eq!(sent, vec![(0, vec![red; 2]), (2, vec![red; 3])]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ping() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[1])]).await;
+        let client = mock.client().await;
+
+        assert!(client.ping().await? > std::time::Duration::ZERO);
+        mock.disconnect_all();
+        assert!(client.ping().await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_no_controllers() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![]).await;
+        let client = mock.client().await;
+
+        assert!(client.get_all_controllers().await?.controllers().is_empty());
+        let err = client.get_controller(0).await.unwrap_err();
+        assert!(err.to_string().contains("No controllers present"), "{err}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_controller_out_of_range() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[1])]).await;
+        let client = mock.client().await;
+
+        assert_eq!(client.get_controller_count().await?, 1);
+        assert!(client.get_controller(0).await.is_ok());
+        assert!(client.get_controller(1).await.is_err());
+        // the cached count is used, get_controller does not request it again
+        assert_eq!(mock.packets_of(PacketId::RequestControllerCount).len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_save_current_as_profile() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![]).await;
+        mock.set_profiles(&["Gaming", "Work"]);
+        let client = mock.client().await;
+
+        client.save_current_as_profile("Gaming").await?;
+        client.save_current_as_profile("Night").await?;
+        assert_eq!(
+            client.get_profiles().await?,
+            vec!["Work", "Gaming", "Night"]
+        );
+        assert_eq!(mock.packets_of(PacketId::RequestDeleteProfile).len(), 1);
+        Ok(())
+    }
+}
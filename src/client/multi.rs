@@ -0,0 +1,182 @@
+use std::sync::Arc;
+
+use tokio::net::ToSocketAddrs;
+
+use crate::{DEFAULT_PROTOCOL, OpenRgbClient, OpenRgbError, OpenRgbResult};
+
+/// Client for multiple OpenRGB servers, e.g. one per machine in a multi-PC setup.
+///
+/// Operations are sent to all servers concurrently and return one result per server,
+/// so a failure on one server does not prevent the others from being updated.
+pub struct MultiServerClient {
+    servers: Vec<(String, Arc<OpenRgbClient>)>,
+}
+
+impl MultiServerClient {
+    /// Connects to the OpenRGB servers at the given addresses.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the servers cannot be connected to.
+    pub async fn connect<A>(addrs: impl IntoIterator<Item = A>) -> OpenRgbResult<Self>
+    where
+        A: ToSocketAddrs + std::fmt::Debug + Copy,
+    {
+        let mut servers = Vec::new();
+        for addr in addrs {
+            let client = OpenRgbClient::connect_to(addr, DEFAULT_PROTOCOL).await?;
+            servers.push((format!("{addr:?}"), Arc::new(client)));
+        }
+        Ok(Self { servers })
+    }
+
+    /// Creates a multi-server client from already connected clients, each labeled with a name (e.g. its address).
+    pub fn from_clients(clients: impl IntoIterator<Item = (String, OpenRgbClient)>) -> Self {
+        let servers = clients
+            .into_iter()
+            .map(|(name, client)| (name, Arc::new(client)))
+            .collect();
+        Self { servers }
+    }
+
+    /// Returns the names of the servers in this client, in the order results are returned.
+    pub fn servers(&self) -> impl Iterator<Item = &str> {
+        self.servers.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Loads the profile with the given name on every server.
+    ///
+    /// Returns the result per server, which is an error if the server does not have this profile.
+    pub async fn load_profile_everywhere(&self, name: &str) -> Vec<(String, OpenRgbResult<()>)> {
+        let name = name.to_string();
+        self.fan_out(move |client| {
+            let name = name.clone();
+            async move {
+                if !client.get_profiles().await?.contains(&name) {
+                    return Err(OpenRgbError::CommandError(format!(
+                        "Profile {name} not found"
+                    )));
+                }
+                client.load_profile(name).await
+            }
+        })
+        .await
+    }
+
+    /// Sets every controller on every server that has a mode with the given name to that mode.
+    ///
+    /// Controllers without this mode are skipped. Returns the result per server,
+    /// which is an error if none of the controllers of that server have the mode.
+    pub async fn set_all_mode_everywhere(&self, name: &str) -> Vec<(String, OpenRgbResult<()>)> {
+        let name = name.to_string();
+        self.fan_out(move |client| {
+            let name = name.clone();
+            async move {
+                let group = client.get_all_controllers().await?;
+                let mut found = false;
+                for controller in &group {
+                    if controller
+                        .data()
+                        .modes
+                        .iter()
+                        .any(|m| m.name.eq_ignore_ascii_case(&name))
+                    {
+                        controller.set_mode_by_name(&name).await?;
+                        found = true;
+                    }
+                }
+                match found {
+                    true => Ok(()),
+                    false => Err(OpenRgbError::CommandError(format!(
+                        "No controller has a mode named {name}"
+                    ))),
+                }
+            }
+        })
+        .await
+    }
+
+    /// Runs `f` for every server concurrently, returning the results in server order.
+    async fn fan_out<F, Fut>(&self, f: F) -> Vec<(String, OpenRgbResult<()>)>
+    where
+        F: Fn(Arc<OpenRgbClient>) -> Fut,
+        Fut: Future<Output = OpenRgbResult<()>> + Send + 'static,
+    {
+        let handles = self
+            .servers
+            .iter()
+            .map(|(name, client)| (name.clone(), tokio::spawn(f(Arc::clone(client)))))
+            .collect::<Vec<_>>();
+        let mut results = Vec::with_capacity(handles.len());
+        for (name, handle) in handles {
+            let result = handle.await.unwrap_or_else(|e| {
+                Err(OpenRgbError::CommandError(format!(
+                    "Server task failed: {e}"
+                )))
+            });
+            if let Err(e) = &result {
+                tracing::warn!("Operation failed on OpenRGB server {name}: {e}");
+            }
+            results.push((name, result));
+        }
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PacketId;
+    use crate::protocol::mock::{MockServer, controller_fixture};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_load_profile_everywhere() -> OpenRgbResult<()> {
+        let first = MockServer::start(5, vec![controller_fixture("Strip", &[1])]).await;
+        first.set_profiles(&["Gaming"]);
+        let second = MockServer::start(5, vec![controller_fixture("Strip", &[1])]).await;
+        second.set_profiles(&["Work"]);
+        let client = MultiServerClient::connect([first.addr(), second.addr()]).await?;
+
+        let results = client.load_profile_everywhere("Gaming").await;
+        assert_eq!(results[0].0, format!("{:?}", first.addr()));
+        assert!(results[0].1.is_ok());
+        assert_eq!(results[1].0, format!("{:?}", second.addr()));
+        assert!(results[1].1.is_err());
+
+        first
+            .wait_for_packets_of(PacketId::RequestLoadProfile, 1)
+            .await;
+        assert!(second.packets_of(PacketId::RequestLoadProfile).is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_all_mode_everywhere() -> OpenRgbResult<()> {
+        let first = MockServer::start(5, vec![controller_fixture("Strip", &[1])]).await;
+        let mut no_static = controller_fixture("Fan", &[1]);
+        no_static.modes.pop();
+        let second = MockServer::start(5, vec![no_static]).await;
+        let client = MultiServerClient::from_clients([
+            ("first".to_string(), first.client().await),
+            ("second".to_string(), second.client().await),
+        ]);
+        assert_eq!(
+            client.servers().collect::<Vec<_>>(),
+            vec!["first", "second"]
+        );
+
+        let results = client.set_all_mode_everywhere("Static").await;
+        assert!(results[0].1.is_ok());
+        assert!(results[1].1.is_err());
+        first
+            .wait_for_packets_of(PacketId::RGBControllerUpdateMode, 1)
+            .await;
+        assert!(
+            second
+                .packets_of(PacketId::RGBControllerUpdateMode)
+                .is_empty()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,306 @@
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+use crate::protocol::{OpenRgbProtocol, PacketId};
+
+/// A notification sent by the OpenRGB server on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notification {
+    /// The list of devices changed, e.g. after a rescan. Previously fetched controllers may be outdated.
+    DeviceListUpdated,
+}
+
+/// A change of the connection status of a `NotificationReader`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// The connection was (re-)established.
+    Connected,
+    /// The connection was lost.
+    Disconnected,
+    /// A reconnect will be attempted after `delay`.
+    Reconnecting {
+        /// Number of this attempt since the connection was lost, starting at 1.
+        attempt: u32,
+        /// Time waited before this attempt.
+        delay: Duration,
+    },
+}
+
+/// Exponential backoff used by a `NotificationReader` to reconnect after losing its connection.
+///
+/// The first attempt waits `initial_delay`, every following attempt waits twice as long, up to `max_delay`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    /// Delay before the first reconnect attempt.
+    pub initial_delay: Duration,
+    /// Maximum delay between reconnect attempts.
+    pub max_delay: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Background task listening for notifications of the OpenRGB server on a dedicated connection.
+///
+/// When the connection is lost (e.g. when OpenRGB restarts), the reader reconnects
+/// using its `ReconnectPolicy`, reporting its status through `connection_events()`.
+/// The reader starts connected, events report changes from then on.
+///
+/// Notifications can be paused with `pause()`, for example during a burst of rescans,
+/// without closing the connection.
+///
+/// The task is stopped when the reader is dropped.
+#[derive(Debug)]
+pub struct NotificationReader {
+    notifications: broadcast::Sender<Notification>,
+    events: broadcast::Sender<ConnectionEvent>,
+    connected: Arc<AtomicBool>,
+    pause: Arc<Mutex<PauseState>>,
+    task: JoinHandle<()>,
+}
+
+/// Whether notifications are paused, and how many arrived while they were.
+#[derive(Debug, Default)]
+struct PauseState {
+    paused: bool,
+    pending: usize,
+}
+
+impl NotificationReader {
+    pub(crate) async fn start(
+        proto: OpenRgbProtocol,
+        policy: ReconnectPolicy,
+    ) -> crate::OpenRgbResult<Self> {
+        let addr = proto.peer_addr().await?;
+        let (notifications, _) = broadcast::channel(64);
+        let (events, _) = broadcast::channel(64);
+        let connected = Arc::new(AtomicBool::new(true));
+        let pause = Arc::new(Mutex::new(PauseState::default()));
+        let task = tokio::spawn(Self::run(
+            proto,
+            addr,
+            policy,
+            notifications.clone(),
+            events.clone(),
+            Arc::clone(&connected),
+            Arc::clone(&pause),
+        ));
+        Ok(Self {
+            notifications,
+            events,
+            connected,
+            pause,
+            task,
+        })
+    }
+
+    async fn run(
+        mut proto: OpenRgbProtocol,
+        addr: SocketAddr,
+        policy: ReconnectPolicy,
+        notifications: broadcast::Sender<Notification>,
+        events: broadcast::Sender<ConnectionEvent>,
+        connected: Arc<AtomicBool>,
+        pause: Arc<Mutex<PauseState>>,
+    ) {
+        let protocol_version = proto.get_protocol_version();
+        loop {
+            match proto.read_notification().await {
+                Ok(id) if id == u32::from(PacketId::DeviceListUpdated) => {
+                    let mut pause = pause.lock().expect("Pause state poisoned");
+                    if pause.paused {
+                        pause.pending += 1;
+                    } else {
+                        let _ = notifications.send(Notification::DeviceListUpdated);
+                    }
+                }
+                Ok(id) => tracing::debug!("Ignoring unexpected notification packet {id}"),
+                Err(e) => {
+                    tracing::warn!("Lost notification connection to {addr}: {e}");
+                    connected.store(false, Ordering::Release);
+                    let _ = events.send(ConnectionEvent::Disconnected);
+                    proto = Self::reconnect(addr, protocol_version, policy, &events).await;
+                    connected.store(true, Ordering::Release);
+                    let _ = events.send(ConnectionEvent::Connected);
+                }
+            }
+        }
+    }
+
+    /// Reconnects to `addr` with exponential backoff, until it succeeds.
+    async fn reconnect(
+        addr: SocketAddr,
+        protocol_version: u32,
+        policy: ReconnectPolicy,
+        events: &broadcast::Sender<ConnectionEvent>,
+    ) -> OpenRgbProtocol {
+        let mut delay = policy.initial_delay;
+        for attempt in 1.. {
+            let _ = events.send(ConnectionEvent::Reconnecting { attempt, delay });
+            tokio::time::sleep(delay).await;
+            match OpenRgbProtocol::connect_to(addr, protocol_version).await {
+                Ok(proto) => return proto,
+                Err(e) => tracing::debug!("Reconnect attempt {attempt} to {addr} failed: {e}"),
+            }
+            delay = (delay * 2).min(policy.max_delay);
+        }
+        unreachable!("reconnect attempts are unbounded")
+    }
+
+    /// Returns a receiver for notifications received from now on.
+    pub fn notifications(&self) -> broadcast::Receiver<Notification> {
+        self.notifications.subscribe()
+    }
+
+    /// Returns a receiver for connection status changes from now on.
+    pub fn connection_events(&self) -> broadcast::Receiver<ConnectionEvent> {
+        self.events.subscribe()
+    }
+
+    /// Returns true if the reader is currently connected to the server.
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Acquire)
+    }
+
+    /// Stops sending notifications until `resume()` is called, keeping the connection open.
+    pub fn pause(&self) {
+        self.pause.lock().expect("Pause state poisoned").paused = true;
+    }
+
+    /// Sends notifications again after `pause()`.
+    ///
+    /// If any `DeviceListUpdated` notifications arrived while paused, a single one is sent now.
+    pub fn resume(&self) {
+        let mut pause = self.pause.lock().expect("Pause state poisoned");
+        pause.paused = false;
+        let pending = std::mem::take(&mut pause.pending);
+        if pending > 0 {
+            tracing::debug!("Resuming notifications, {pending} arrived while paused");
+            let _ = self.notifications.send(Notification::DeviceListUpdated);
+        }
+    }
+
+    /// Returns true if notifications are paused, see `pause()`.
+    pub fn is_paused(&self) -> bool {
+        self.pause.lock().expect("Pause state poisoned").paused
+    }
+}
+
+impl Drop for NotificationReader {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::OpenRgbResult;
+    use crate::protocol::mock::MockServer;
+
+    #[tokio::test]
+    async fn test_pause_coalesces() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![]).await;
+        let client = mock.client().await;
+        let reader = client.notification_reader().await?;
+        let mut notifications = reader.notifications();
+        mock.wait_for_open_connections(2).await;
+
+        reader.pause();
+        assert!(reader.is_paused());
+        for _ in 0..3 {
+            mock.notify(PacketId::DeviceListUpdated);
+        }
+        // wait until the reader has seen all of them, so none arrive after resuming
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while reader.pause.lock().unwrap().pending < 3 {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .expect("notifications not received while paused");
+        assert!(notifications.try_recv().is_err());
+
+        reader.resume();
+        assert!(!reader.is_paused());
+        assert_eq!(
+            notifications.recv().await.unwrap(),
+            Notification::DeviceListUpdated
+        );
+        assert!(notifications.try_recv().is_err());
+
+        // nothing arrived while paused, so resuming sends nothing
+        reader.pause();
+        reader.resume();
+        assert!(notifications.try_recv().is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_events() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![]).await;
+        let client = mock.client().await;
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(15),
+        };
+        let reader = client.notification_reader_with(policy).await?;
+        let mut notifications = reader.notifications();
+        let mut events = reader.connection_events();
+        mock.wait_for_open_connections(2).await;
+
+        mock.notify(PacketId::DeviceListUpdated);
+        assert_eq!(
+            notifications.recv().await.unwrap(),
+            Notification::DeviceListUpdated
+        );
+
+        mock.refuse_connections(2);
+        mock.disconnect_all();
+        let mut received = Vec::new();
+        while received.last() != Some(&ConnectionEvent::Connected) {
+            received.push(events.recv().await.unwrap());
+        }
+        assert_eq!(
+            received,
+            vec![
+                ConnectionEvent::Disconnected,
+                ConnectionEvent::Reconnecting {
+                    attempt: 1,
+                    delay: Duration::from_millis(10)
+                },
+                ConnectionEvent::Reconnecting {
+                    attempt: 2,
+                    delay: Duration::from_millis(15)
+                },
+                ConnectionEvent::Reconnecting {
+                    attempt: 3,
+                    delay: Duration::from_millis(15)
+                },
+                ConnectionEvent::Connected,
+            ]
+        );
+        assert!(reader.is_connected());
+
+        // the reader receives notifications on its new connection
+        mock.notify(PacketId::DeviceListUpdated);
+        assert_eq!(
+            notifications.recv().await.unwrap(),
+            Notification::DeviceListUpdated
+        );
+        Ok(())
+    }
+}
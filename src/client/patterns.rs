@@ -0,0 +1,94 @@
+//! Iterators that build color buffers for common static patterns.
+//!
+//! Every pattern yields exactly `len` colors, so it can be passed directly to `Controller::set_leds()`.
+
+use crate::Color;
+
+/// Returns `len` colors of stripes that are `width` LEDs wide, cycling through `colors`.
+///
+/// A `width` of 0 is treated as 1. If `colors` is empty, all LEDs are black.
+pub fn stripes(colors: &[Color], width: usize, len: usize) -> impl Iterator<Item = Color> {
+    let width = width.max(1);
+    (0..len).map(move |i| match colors.len() {
+        0 => Color::default(),
+        n => colors[(i / width) % n],
+    })
+}
+
+/// Returns `len` colors alternating between `a` and `b`, starting with `a`.
+pub fn checker(a: Color, b: Color, len: usize) -> impl Iterator<Item = Color> {
+    (0..len).map(move |i| if i % 2 == 0 { a } else { b })
+}
+
+/// Returns `len` colors forming one full rainbow at full saturation and brightness.
+///
+/// `offset` shifts the rainbow by a fraction of its length, so animating it from 0.0 to 1.0 rotates it once.
+pub fn rainbow(len: usize, offset: f32) -> impl Iterator<Item = Color> {
+    (0..len).map(move |i| {
+        let hue = (i as f32 / len as f32 + offset).rem_euclid(1.0);
+        hue_to_color(hue)
+    })
+}
+
+/// Converts `hue` in `[0, 1)` to a color at full saturation and brightness.
+fn hue_to_color(hue: f32) -> Color {
+    let h = hue * 6.0;
+    let x = ((1.0 - (h % 2.0 - 1.0).abs()) * 255.0).round() as u8;
+    match h as u32 {
+        0 => Color::new(255, x, 0),
+        1 => Color::new(x, 255, 0),
+        2 => Color::new(0, 255, x),
+        3 => Color::new(0, x, 255),
+        4 => Color::new(x, 0, 255),
+        _ => Color::new(255, 0, x),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color = Color::new(255, 0, 0);
+    const GREEN: Color = Color::new(0, 255, 0);
+    const BLUE: Color = Color::new(0, 0, 255);
+
+    #[test]
+    fn test_stripes() {
+        let colors = stripes(&[RED, GREEN, BLUE], 2, 7).collect::<Vec<_>>();
+        assert_eq!(colors, vec![RED, RED, GREEN, GREEN, BLUE, BLUE, RED]);
+        assert_eq!(
+            stripes(&[], 2, 3).collect::<Vec<_>>(),
+            vec![Color::default(); 3]
+        );
+        assert_eq!(
+            stripes(&[RED, GREEN], 0, 3).collect::<Vec<_>>(),
+            vec![RED, GREEN, RED]
+        );
+    }
+
+    #[test]
+    fn test_checker() {
+        let colors = checker(RED, BLUE, 5).collect::<Vec<_>>();
+        assert_eq!(colors, vec![RED, BLUE, RED, BLUE, RED]);
+        assert_eq!(checker(RED, BLUE, 0).count(), 0);
+    }
+
+    #[test]
+    fn test_rainbow() {
+        let colors = rainbow(6, 0.0).collect::<Vec<_>>();
+        assert_eq!(
+            colors,
+            vec![
+                RED,
+                Color::new(255, 255, 0),
+                GREEN,
+                Color::new(0, 255, 255),
+                BLUE,
+                Color::new(255, 0, 255),
+            ]
+        );
+        // a full offset wraps around to the same rainbow
+        assert_eq!(rainbow(6, 1.0).collect::<Vec<_>>(), colors);
+        assert_eq!(rainbow(6, 0.5).next(), Some(Color::new(0, 255, 255)));
+    }
+}
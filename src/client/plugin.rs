@@ -0,0 +1,98 @@
+use crate::protocol::OpenRgbProtocol;
+use crate::{OpenRgbError, OpenRgbResult, PluginData};
+
+/// A plugin installed on the OpenRGB server, bound to the connection it was found on.
+///
+/// Use `OpenRgbClient::find_plugin` to get one.
+pub struct Plugin {
+    proto: OpenRgbProtocol,
+    data: PluginData,
+}
+
+impl std::fmt::Debug for Plugin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Plugin")
+            .field("data", &self.data)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Plugin {
+    pub(crate) fn new(proto: OpenRgbProtocol, data: PluginData) -> Self {
+        Self { proto, data }
+    }
+
+    /// Returns the `PluginData` for this plugin.
+    pub fn data(&self) -> &PluginData {
+        &self.data
+    }
+
+    /// Returns the name of this plugin.
+    pub fn name(&self) -> &str {
+        self.data.name()
+    }
+
+    /// Returns the id of this plugin, which is used to address plugin specific commands.
+    pub fn id(&self) -> u32 {
+        self.data.index()
+    }
+
+    /// Returns the protocol version of this plugin, which can be used to gate plugin specific commands.
+    pub fn protocol_version(&self) -> u32 {
+        self.data.plugin_protocol_version()
+    }
+
+    /// Fetches the data of this plugin from the server again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the plugin is no longer installed.
+    pub async fn sync_plugin_data(&mut self) -> OpenRgbResult<()> {
+        self.data = find_plugin_data(&self.proto, self.data.name()).await?;
+        Ok(())
+    }
+}
+
+/// Returns the data of the installed plugin with the given `name`, ignoring case.
+pub(crate) async fn find_plugin_data(
+    proto: &OpenRgbProtocol,
+    name: &str,
+) -> OpenRgbResult<PluginData> {
+    proto
+        .get_plugins()
+        .await?
+        .into_iter()
+        .find(|p| p.matches(name))
+        .ok_or(OpenRgbError::CommandError(format!(
+            "Plugin {name} is not installed"
+        )))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::protocol::mock::MockServer;
+    use crate::{OpenRgbResult, PluginData};
+
+    #[tokio::test]
+    async fn test_find_plugin() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![]).await;
+        mock.set_plugins(vec![
+            PluginData::new("Effects", 0, 2),
+            PluginData::new("Visual Map", 1, 1),
+        ]);
+        let client = mock.client().await;
+
+        let mut plugin = client.find_plugin("visual map").await?;
+        assert_eq!(plugin.name(), "Visual Map");
+        assert_eq!(plugin.id(), 1);
+        assert_eq!(plugin.protocol_version(), 1);
+        assert!(client.find_plugin("E1.31").await.is_err());
+
+        mock.set_plugins(vec![PluginData::new("Visual Map", 0, 1)]);
+        plugin.sync_plugin_data().await?;
+        assert_eq!(plugin.id(), 0);
+        mock.set_plugins(vec![]);
+        assert!(plugin.sync_plugin_data().await.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{Controller, OpenRgbClient, OpenRgbError, OpenRgbResult, protocol::OpenRgbProtocol};
+
+/// A fixed number of connections to the same OpenRGB server.
+///
+/// Giving every controller its own connection (see `Controller::connect_isolated()`) can open many sockets,
+/// which some servers limit. A pool caps the number of connections and hands them out either
+/// round-robin (`next_client()`) or per controller (`get_controller()`).
+///
+/// Connections are closed when the pool and all clients and controllers using them are dropped,
+/// or immediately with `close_all()`.
+pub struct ConnectionPool {
+    connections: Vec<OpenRgbProtocol>,
+    next: AtomicUsize,
+}
+
+impl ConnectionPool {
+    /// Opens `size` new connections to the server `client` is connected to.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `size` is 0 or a connection could not be opened.
+    pub async fn new(client: &OpenRgbClient, size: usize) -> OpenRgbResult<Self> {
+        if size == 0 {
+            return Err(OpenRgbError::CommandError(
+                "Connection pool needs at least one connection".to_string(),
+            ));
+        }
+        let mut connections = Vec::with_capacity(size);
+        for _ in 0..size {
+            connections.push(client.proto.connect_clone().await?);
+        }
+        Ok(Self {
+            connections,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// Returns the number of connections in this pool.
+    pub fn len(&self) -> usize {
+        self.connections.len()
+    }
+
+    /// Returns true if this pool has no connections, which is only the case after `close_all()`.
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+
+    /// Returns a client using the next connection in this pool, cycling through all connections.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pool was closed.
+    pub fn next_client(&self) -> OpenRgbResult<OpenRgbClient> {
+        let idx = self.next.fetch_add(1, Ordering::Relaxed);
+        let proto = self.connection(idx)?.clone();
+        Ok(OpenRgbClient::new(proto))
+    }
+
+    /// Gets the controller with index `i`, using the connection assigned to that controller.
+    ///
+    /// Controllers are spread over the connections by their index, so the same controller always uses the same connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pool was closed or the controller could not be fetched.
+    pub async fn get_controller(&self, i: usize) -> OpenRgbResult<Controller> {
+        let proto = self.connection(i)?.clone();
+        OpenRgbClient::new(proto).get_controller(i).await
+    }
+
+    /// Closes all connections in this pool.
+    ///
+    /// Clients and controllers obtained from this pool can no longer communicate with the server afterwards.
+    pub async fn close_all(&mut self) -> OpenRgbResult<()> {
+        for connection in self.connections.drain(..) {
+            connection.close().await?;
+        }
+        Ok(())
+    }
+
+    fn connection(&self, idx: usize) -> OpenRgbResult<&OpenRgbProtocol> {
+        if self.connections.is_empty() {
+            return Err(OpenRgbError::CommandError(
+                "Connection pool is closed".to_string(),
+            ));
+        }
+        Ok(&self.connections[idx % self.connections.len()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::OpenRgbResult;
+    use crate::protocol::mock::{MockServer, controller_fixture};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pool_reuses_connections() -> OpenRgbResult<()> {
+        let controllers = (0..4)
+            .map(|i| controller_fixture(&format!("Strip {i}"), &[1]))
+            .collect();
+        let mock = MockServer::start(5, controllers).await;
+        let client = mock.client().await;
+        let mut pool = ConnectionPool::new(&client, 2).await?;
+        assert_eq!(pool.len(), 2);
+
+        let mut controllers = Vec::new();
+        for i in 0..4 {
+            controllers.push(pool.get_controller(i).await?);
+        }
+        let first = pool.next_client()?;
+        let second = pool.next_client()?;
+        let third = pool.next_client()?;
+        assert_eq!(mock.total_connections(), 3);
+        assert!(
+            controllers[0]
+                .proto()
+                .same_connection(controllers[2].proto())
+        );
+        assert!(
+            !controllers[0]
+                .proto()
+                .same_connection(controllers[1].proto())
+        );
+        assert!(first.proto.same_connection(&third.proto));
+        assert!(!first.proto.same_connection(&second.proto));
+
+        pool.close_all().await?;
+        assert!(pool.is_empty());
+        assert!(pool.next_client().is_err());
+        mock.wait_for_open_connections(1).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pool_closes_on_drop() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[1])]).await;
+        let client = mock.client().await;
+        let pool = ConnectionPool::new(&client, 3).await?;
+        mock.wait_for_open_connections(4).await;
+
+        drop(pool);
+        mock.wait_for_open_connections(1).await;
+        Ok(())
+    }
+}
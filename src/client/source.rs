@@ -0,0 +1,89 @@
+use crate::Color;
+
+/// Source of the colors for every LED of a controller, see `Controller::set_leds_from()`.
+///
+/// Every source returns exactly `num_leds` colors.
+pub trait ColorSource {
+    /// Returns one color per LED, `num_leds` colors in total.
+    fn colors(&self, num_leds: usize) -> Vec<Color>;
+}
+
+/// Returns `colors` padded with black or truncated, so that it has exactly `num_leds` colors.
+pub(crate) fn fit_colors(colors: &[Color], num_leds: usize) -> Vec<Color> {
+    let mut colors = colors.to_vec();
+    colors.resize(num_leds, Color::default());
+    colors
+}
+
+/// Pads with black or truncates the colors to `num_leds`.
+impl ColorSource for &[Color] {
+    fn colors(&self, num_leds: usize) -> Vec<Color> {
+        fit_colors(self, num_leds)
+    }
+}
+
+/// Pads with black or truncates the colors to `num_leds`.
+impl ColorSource for Vec<Color> {
+    fn colors(&self, num_leds: usize) -> Vec<Color> {
+        self.as_slice().colors(num_leds)
+    }
+}
+
+/// Sets every LED to this color.
+impl ColorSource for Color {
+    fn colors(&self, num_leds: usize) -> Vec<Color> {
+        vec![*self; num_leds]
+    }
+}
+
+/// Calls the closure with the index of every LED.
+impl<F> ColorSource for F
+where
+    F: Fn(usize) -> Color,
+{
+    fn colors(&self, num_leds: usize) -> Vec<Color> {
+        (0..num_leds).map(self).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color = Color::new(255, 0, 0);
+
+    #[test]
+    fn test_slice() {
+        let colors = [RED, RED];
+        assert_eq!(
+            colors.as_slice().colors(3),
+            vec![RED, RED, Color::default()]
+        );
+        assert_eq!(colors.as_slice().colors(1), vec![RED]);
+    }
+
+    #[test]
+    fn test_vec() {
+        assert_eq!(vec![RED; 4].colors(2), vec![RED; 2]);
+        assert_eq!(Vec::new().colors(2), vec![Color::default(); 2]);
+    }
+
+    #[test]
+    fn test_solid() {
+        assert_eq!(RED.colors(3), vec![RED; 3]);
+        assert!(RED.colors(0).is_empty());
+    }
+
+    #[test]
+    fn test_closure() {
+        let gradient = |i: usize| Color::new(i as u8, 0, 0);
+        assert_eq!(
+            gradient.colors(3),
+            vec![
+                Color::new(0, 0, 0),
+                Color::new(1, 0, 0),
+                Color::new(2, 0, 0)
+            ]
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Color, Controller, OpenRgbError, OpenRgbResult, data::Direction};
+
+/// State of all controllers, see `OpenRgbClient::export_state()`.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ClientState {
+    controllers: Vec<ControllerState>,
+}
+
+/// Identity, active mode and colors of a single controller.
+#[derive(Debug, Serialize, Deserialize)]
+struct ControllerState {
+    name: String,
+    location: String,
+    serial: String,
+    device_type: u32,
+    mode: ModeState,
+    colors: Vec<Color>,
+}
+
+/// Settings of the active mode of a controller.
+#[derive(Debug, Serialize, Deserialize)]
+struct ModeState {
+    index: u32,
+    name: String,
+    speed: u32,
+    brightness: Option<u32>,
+    direction: Option<u32>,
+    colors: Vec<Color>,
+}
+
+impl ClientState {
+    pub(crate) fn new(controllers: &[Controller]) -> OpenRgbResult<Self> {
+        let controllers = controllers
+            .iter()
+            .map(ControllerState::new)
+            .collect::<OpenRgbResult<_>>()?;
+        Ok(Self { controllers })
+    }
+
+    pub(crate) fn to_json(&self) -> OpenRgbResult<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| OpenRgbError::CommandError(format!("Failed encoding state as JSON: {e}")))
+    }
+
+    pub(crate) fn from_json(json: &str) -> OpenRgbResult<Self> {
+        serde_json::from_str(json)
+            .map_err(|e| OpenRgbError::CommandError(format!("Failed decoding state JSON: {e}")))
+    }
+
+    /// Applies the saved state to the matching controllers, returning one result per saved controller.
+    pub(crate) async fn apply(
+        &self,
+        controllers: Vec<Controller>,
+    ) -> Vec<(String, OpenRgbResult<()>)> {
+        let mut controllers = controllers.into_iter().map(Some).collect::<Vec<_>>();
+        let mut results = Vec::with_capacity(self.controllers.len());
+        for state in &self.controllers {
+            let controller = controllers
+                .iter_mut()
+                .find(|c| c.as_ref().is_some_and(|c| state.matches(c)))
+                .and_then(Option::take);
+            let result = match controller {
+                Some(controller) => state.apply(&controller).await,
+                None => Err(OpenRgbError::CommandError(format!(
+                    "Controller {} at {} not found",
+                    state.name, state.location
+                ))),
+            };
+            results.push((state.name.clone(), result));
+        }
+        results
+    }
+}
+
+impl ControllerState {
+    fn new(controller: &Controller) -> OpenRgbResult<Self> {
+        let data = controller.data();
+        let mode = controller.active_mode()?;
+        Ok(Self {
+            name: data.name.clone(),
+            location: data.location.clone(),
+            serial: data.serial.clone(),
+            device_type: u32::from(data.device_type),
+            mode: ModeState {
+                index: mode.index,
+                name: mode.name.clone(),
+                speed: mode.speed,
+                brightness: mode.brightness(),
+                direction: mode.direction().map(u32::from),
+                colors: mode.colors.clone(),
+            },
+            colors: data.colors.clone(),
+        })
+    }
+
+    /// Returns true if `controller` is the device this state was exported from.
+    fn matches(&self, controller: &Controller) -> bool {
+        let data = controller.data();
+        data.name == self.name
+            && data.location == self.location
+            && data.serial == self.serial
+            && u32::from(data.device_type) == self.device_type
+    }
+
+    async fn apply(&self, controller: &Controller) -> OpenRgbResult<()> {
+        let mut mode = controller
+            .data()
+            .modes
+            .get(self.mode.index as usize)
+            .filter(|m| m.name == self.mode.name)
+            .ok_or(OpenRgbError::CommandError(format!(
+                "Controller {} has no mode {} at index {}",
+                self.name, self.mode.name, self.mode.index
+            )))?
+            .clone();
+        mode.set_speed(self.mode.speed);
+        if let Some(brightness) = self.mode.brightness {
+            mode.set_brightness(brightness);
+        }
+        if let Some(direction) = self.mode.direction {
+            mode.set_direction(Direction::try_from(direction)?);
+        }
+        mode.colors = self.mode.colors.clone();
+        controller.mode_changed();
+        controller
+            .proto()
+            .update_mode(controller.id() as u32, &mode)
+            .await?;
+        if !self.colors.is_empty() {
+            controller.set_leds(self.colors.iter().copied()).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::ModeFlag;
+    use crate::protocol::mock::{MockServer, controller_fixture};
+    use crate::{Color, OpenRgbResult, PacketId};
+
+    #[tokio::test]
+    async fn test_round_trip() -> OpenRgbResult<()> {
+        let strip = |speed: u32, active_mode: i32| {
+            let mut strip = controller_fixture("Strip", &[2]);
+            strip.active_mode = active_mode;
+            strip.modes[1].flags |= ModeFlag::HasSpeed;
+            strip.modes[1].speed_max = 10;
+            strip.modes[1].speed = speed;
+            strip.modes[1].colors = vec![Color::new(0, 255, 0)];
+            strip.colors = vec![Color::new(1, 2, 3), Color::new(4, 5, 6)];
+            strip
+        };
+        let fan = || {
+            let mut fan = controller_fixture("Fan", &[1]);
+            fan.colors = vec![Color::new(9, 9, 9)];
+            fan
+        };
+        let mock = MockServer::start(5, vec![strip(7, 1), fan()]).await;
+        let client = mock.client().await;
+
+        let json = client.export_state().await?;
+
+        // devices were reordered and changed since the export
+        mock.set_controllers(vec![fan(), strip(1, 0), controller_fixture("Mouse", &[1])]);
+
+        let results = client.import_state(&json).await?;
+        let names = results.iter().map(|(n, _)| n.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Strip", "Fan"]);
+        assert!(results.iter().all(|(_, r)| r.is_ok()));
+
+        let restored = client.get_controller(1).await?;
+        assert_eq!(restored.name(), "Strip");
+        assert_eq!(restored.active_mode()?, &strip(7, 1).modes[1]);
+
+        let leds = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateLeds, 2)
+            .await
+            .iter()
+            .map(|p| {
+                let mut msg = p.message(5);
+                let _data_size = msg.read_u32()?;
+                Ok((p.device_id, msg.read_value::<Vec<Color>>()?))
+            })
+            .collect::<OpenRgbResult<Vec<_>>>()?;
+        assert_eq!(
+            leds,
+            vec![
+                (1, vec![Color::new(1, 2, 3), Color::new(4, 5, 6)]),
+                (0, vec![Color::new(9, 9, 9)]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_import_missing_device() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2])]).await;
+        let client = mock.client().await;
+
+        let json = client.export_state().await?;
+        mock.set_controllers(vec![]);
+        let results = client.import_state(&json).await?;
+        assert_eq!(results.len(), 1);
+        assert!(results[0].1.is_err());
+        assert!(client.import_state("not json").await.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{
+    Controller,
+    data::{DeviceType, ZoneType},
+};
+
+/// Snapshot of a controller and its zones and segments, see `OpenRgbClient::device_tree()`.
+///
+/// With the `serde` feature, the tree can be serialized, e.g. to export it as JSON.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DeviceNode {
+    /// Id of the controller.
+    pub id: usize,
+    /// Name of the controller.
+    pub name: String,
+    /// Device type of the controller.
+    pub device_type: DeviceType,
+    /// Number of LEDs of the controller.
+    pub num_leds: usize,
+    /// Zones of the controller.
+    pub zones: Vec<ZoneNode>,
+}
+
+/// Snapshot of a zone in a [`DeviceNode`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ZoneNode {
+    /// Id of the zone.
+    pub id: usize,
+    /// Name of the zone.
+    pub name: String,
+    /// Type of the zone.
+    pub zone_type: ZoneType,
+    /// Number of LEDs in the zone.
+    pub num_leds: usize,
+    /// Index of the first LED of the zone in the controller's LED array.
+    pub offset: usize,
+    /// Segments of the zone, empty on protocol versions < 4.
+    pub segments: Vec<SegmentNode>,
+}
+
+/// Snapshot of a segment in a [`ZoneNode`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SegmentNode {
+    /// Id of the segment.
+    pub id: usize,
+    /// Name of the segment.
+    pub name: String,
+    /// Type of the segment.
+    pub segment_type: ZoneType,
+    /// Number of LEDs in the segment.
+    pub num_leds: usize,
+    /// Index of the first LED of the segment in the controller's LED array.
+    pub offset: usize,
+}
+
+impl DeviceNode {
+    pub(crate) fn new(controller: &Controller) -> Self {
+        let mut offset = 0;
+        let zones = controller
+            .data()
+            .zones
+            .iter()
+            .enumerate()
+            .map(|(id, zone)| {
+                let segments = zone
+                    .segments
+                    .value()
+                    .into_iter()
+                    .flatten()
+                    .enumerate()
+                    .map(|(id, segment)| SegmentNode {
+                        id,
+                        name: segment.name().to_string(),
+                        segment_type: segment.segment_type(),
+                        num_leds: segment.led_count() as usize,
+                        offset: offset + segment.start_led() as usize,
+                    })
+                    .collect();
+                let node = ZoneNode {
+                    id,
+                    name: zone.name.clone(),
+                    zone_type: zone.zone_type,
+                    num_leds: zone.leds_count as usize,
+                    offset,
+                    segments,
+                };
+                offset += node.num_leds;
+                node
+            })
+            .collect();
+        Self {
+            id: controller.id(),
+            name: controller.name().to_string(),
+            device_type: controller.data().device_type,
+            num_leds: controller.num_leds(),
+            zones,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::OpenRgbResult;
+    use crate::data::SegmentData;
+    use crate::protocol::mock::{MockServer, controller_fixture};
+
+    #[tokio::test]
+    async fn test_device_tree() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[3, 10]);
+        let segments = data.zones[1].segments.value_mut().unwrap();
+        segments.push(SegmentData::new("Left", 0, 4));
+        segments.push(SegmentData::new("Right", 4, 6));
+        let mock = MockServer::start(5, vec![controller_fixture("Fan", &[2]), data]).await;
+        let client = mock.client().await;
+
+        let tree = client.device_tree().await?;
+        assert_eq!(tree.len(), 2);
+        let strip = &tree[1];
+        assert_eq!(
+            (strip.id, strip.name.as_str(), strip.num_leds),
+            (1, "Strip", 13)
+        );
+
+        let controller = client.get_controller(1).await?;
+        for (node, zone) in strip.zones.iter().zip(controller.get_all_zones()) {
+            assert_eq!(node.offset, zone.offset());
+            assert_eq!(node.num_leds, zone.num_leds());
+        }
+        let offsets = strip.zones[1]
+            .segments
+            .iter()
+            .map(|s| (s.name.as_str(), s.offset, s.num_leds))
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, vec![("Left", 3, 4), ("Right", 7, 6)]);
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[tokio::test]
+    async fn test_device_tree_json() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[3])]).await;
+        let tree = mock.client().await.device_tree().await?;
+
+        let json = serde_json::to_value(&tree).unwrap();
+        assert_eq!(json[0]["name"], "Strip");
+        assert_eq!(json[0]["zones"][0]["num_leds"], 3);
+        Ok(())
+    }
+}
@@ -1,9 +1,57 @@
+use std::time::Duration;
+
+use array2d::Array2D;
+
 use crate::{
     Color, Controller, OpenRgbError, OpenRgbResult,
-    client::{command::UpdateCommand, segment::Segment},
-    data::{SegmentData, ZoneData},
+    client::{
+        command::UpdateCommand,
+        controller::{ColorOrigin, LedTarget},
+        segment::Segment,
+        source::fit_colors,
+    },
+    data::{Direction, ModeData, ModeFlag, SegmentData, ZoneData},
 };
 
+/// The suggested position of a matrix zone in a grid combining all matrix zones of a controller.
+///
+/// See `Controller::matrix_layout`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ZoneMatrixPlacement {
+    zone_id: usize,
+    width: usize,
+    height: usize,
+    row_offset: usize,
+    column_offset: usize,
+}
+
+impl ZoneMatrixPlacement {
+    /// Returns the ID of the zone this placement is for.
+    pub fn zone_id(&self) -> usize {
+        self.zone_id
+    }
+
+    /// Returns the number of columns of the zone matrix.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the number of rows of the zone matrix.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the row in the combined grid at which the zone matrix starts.
+    pub fn row_offset(&self) -> usize {
+        self.row_offset
+    }
+
+    /// Returns the column in the combined grid at which the zone matrix starts.
+    pub fn column_offset(&self) -> usize {
+        self.column_offset
+    }
+}
+
 /// A zone in a controller, which contains one or more LEDs.
 ///
 /// Zones can also contain segments, which are user-created subdivisions of the zone.
@@ -40,6 +88,18 @@ impl<'a> Zone<'a> {
             .expect("Invalid zone was created") // should be unreachable
     }
 
+    /// Returns true if the protocol supports segments and this zone has at least one segment.
+    ///
+    /// See `Controller::supports_segments()`.
+    pub fn has_segments(&self) -> bool {
+        self.controller.supports_segments()
+            && self
+                .data()
+                .segments
+                .value()
+                .is_some_and(|segments| !segments.is_empty())
+    }
+
     /// Returns the segment with the given `segment_id`.
     pub fn get_segment(&'a self, segment_id: usize) -> OpenRgbResult<Segment<'a>> {
         let is_valid = self
@@ -67,11 +127,109 @@ impl<'a> Zone<'a> {
             .map(move |(id, _)| Segment::new(self, id))
     }
 
+    /// Returns the matrix map of this zone, which maps (row, column) positions to LED indices in the zone.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if this zone is not a matrix.
+    pub fn matrix(&self) -> OpenRgbResult<&Array2D<u32>> {
+        self.data()
+            .matrix
+            .as_ref()
+            .ok_or(OpenRgbError::CommandError(format!(
+                "Zone {} for controller {} is not a matrix",
+                self.zone_id,
+                self.controller.name()
+            )))
+    }
+
+    /// Returns the cells of this zone's matrix in row-major order, as `(row, column, led)`.
+    ///
+    /// `led` is the index in the controller's LED array, or `None` if the cell has no LED.
+    /// Yields nothing if this zone is not a matrix.
+    pub fn matrix_iter(&self) -> impl Iterator<Item = (usize, usize, Option<usize>)> + '_ {
+        let offset = self.offset();
+        self.data().matrix.iter().flat_map(move |matrix| {
+            let columns = matrix.num_columns();
+            matrix
+                .elements_row_major_iter()
+                .enumerate()
+                .map(move |(i, &led)| {
+                    let led = (led != u32::MAX).then_some(offset + led as usize);
+                    (i / columns, i % columns, led)
+                })
+        })
+    }
+
+    /// Fetches the current colors of this zone and arranges them like `matrix()`.
+    ///
+    /// Cells without an LED are `None`, as are cells whose LED has no color because the controller reported too few colors.
+    /// The cached data of the controller is not updated.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if this zone is not a matrix or the controller data could not be fetched.
+    pub async fn matrix_colors(&self) -> OpenRgbResult<Array2D<Option<Color>>> {
+        let matrix = self.matrix()?;
+        let data = self
+            .controller
+            .proto()
+            .get_controller(self.controller.id() as u32)
+            .await?;
+        let cells = self
+            .matrix_iter()
+            .map(|(_, _, led)| led.and_then(|led| data.colors.get(led).copied()))
+            .collect::<Vec<_>>();
+        Array2D::from_row_major(&cells, matrix.num_rows(), matrix.num_columns()).map_err(|e| {
+            OpenRgbError::CommandError(format!(
+                "Failed building color matrix for zone {}: {e:?}",
+                self.zone_id
+            ))
+        })
+    }
+
+    /// Returns the placement of this zone's matrix, with its top left corner at (`row_offset`, `column_offset`).
+    pub(crate) fn matrix_placement(
+        &self,
+        row_offset: usize,
+        column_offset: usize,
+    ) -> OpenRgbResult<ZoneMatrixPlacement> {
+        let matrix = self.matrix()?;
+        Ok(ZoneMatrixPlacement {
+            zone_id: self.zone_id,
+            width: matrix.num_columns(),
+            height: matrix.num_rows(),
+            row_offset,
+            column_offset,
+        })
+    }
+
     /// Returns the number of leds in this zone.
     pub fn num_leds(&self) -> usize {
         self.data().leds_count as usize
     }
 
+    /// Returns the minimum and maximum number of LEDs this zone can be resized to.
+    ///
+    /// The protocol does not tell whether a zone can only be resized in fixed steps,
+    /// so callers should just keep the new size within these bounds.
+    pub fn resize_bounds(&self) -> (u32, u32) {
+        (self.data().leds_min, self.data().leds_max)
+    }
+
+    /// Returns true if this zone can be resized, which is the case if `leds_min != leds_max`.
+    pub fn is_resizable(&self) -> bool {
+        let (min, max) = self.resize_bounds();
+        min != max
+    }
+
+    /// Returns `colors` padded with black or truncated, so that it has exactly `num_leds()` colors.
+    ///
+    /// Use this to inspect the colors that fit this zone before sending them.
+    pub fn fit_colors(&self, colors: &[Color]) -> Vec<Color> {
+        fit_colors(colors, self.num_leds())
+    }
+
     /// Returns the offset of this zone in the controller's LED array.
     pub fn offset(&self) -> usize {
         self.controller
@@ -107,12 +265,204 @@ impl<'a> Zone<'a> {
         self.controller.set_led(idx, color).await
     }
 
+    /// Sets only the LEDs at the given matrix `cells`, given as `((row, column), color)`.
+    ///
+    /// Positions are translated to LEDs using `matrix()`, other LEDs are left untouched.
+    /// Nothing is sent if any of the cells is invalid.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if this zone is not a matrix, or a cell is out of bounds or maps to a gap (`u32::MAX`).
+    pub async fn update_matrix_sparse(
+        &self,
+        cells: &[((usize, usize), Color)],
+    ) -> OpenRgbResult<()> {
+        let matrix = self.matrix()?;
+        let leds = cells
+            .iter()
+            .map(|&((row, column), color)| match matrix.get(row, column) {
+                Some(&u32::MAX) => Err(OpenRgbError::CommandError(format!(
+                    "Matrix cell ({row}, {column}) of zone {} has no LED",
+                    self.zone_id
+                ))),
+                Some(&led) => Ok((led as usize, color)),
+                None => Err(OpenRgbError::CommandError(format!(
+                    "Matrix cell ({row}, {column}) out of bounds for zone {} with {}x{} matrix",
+                    self.zone_id,
+                    matrix.num_rows(),
+                    matrix.num_columns()
+                ))),
+            })
+            .collect::<OpenRgbResult<Vec<_>>>()?;
+        for (led, color) in leds {
+            self.set_led(led, color).await?;
+        }
+        Ok(())
+    }
+
     /// Sets all LEDs in this zone to the given `color`.
     pub async fn set_all_leds(&self, color: Color) -> OpenRgbResult<()> {
         let colors = vec![color; self.data().leds_count as usize];
         self.set_leds(colors).await
     }
 
+    /// Sets every LED of this zone to the color returned by `f(index, num_leds)`.
+    ///
+    /// `index` is the index of the LED within this zone, `num_leds` the number of LEDs in this zone.
+    pub async fn update_with<F>(&self, f: F) -> OpenRgbResult<()>
+    where
+        F: Fn(usize, usize) -> Color,
+    {
+        let num_leds = self.num_leds();
+        self.controller
+            .set_zone_leds(self.zone_id, (0..num_leds).map(|i| f(i, num_leds)))
+            .await
+    }
+
+    /// Shows `palette` repeated across this zone and shifts it by one LED every `interval`, for `cycles` frames.
+    ///
+    /// The controller is set to a controllable mode first.
+    /// Afterwards, the previous mode and the colors of this zone last synced from the server are restored,
+    /// also if showing the palette failed partway.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `palette` is empty.
+    pub async fn cycle_palette(
+        &self,
+        palette: &[Color],
+        interval: Duration,
+        cycles: u32,
+    ) -> OpenRgbResult<()> {
+        if palette.is_empty() {
+            return Err(OpenRgbError::CommandError(
+                "Cannot cycle an empty palette".to_string(),
+            ));
+        }
+        let previous_mode = self.controller.active_mode()?.clone();
+        let shown = self.show_palette(palette, interval, cycles).await;
+        let restored = self.restore_colors(&previous_mode).await;
+        shown.and(restored)
+    }
+
+    /// Switches to a controllable mode and shows the frames of `cycle_palette()`.
+    async fn show_palette(
+        &self,
+        palette: &[Color],
+        interval: Duration,
+        cycles: u32,
+    ) -> OpenRgbResult<()> {
+        self.controller.set_controllable_mode().await?;
+        let len = palette.len();
+        for frame in 0..cycles as usize {
+            let shift = len - frame % len;
+            let colors = (0..self.num_leds()).map(|i| palette[(i + shift) % len]);
+            self.controller.set_zone_leds(self.zone_id, colors).await?;
+            tokio::time::sleep(interval).await;
+        }
+        Ok(())
+    }
+
+    /// Restores the colors of this zone last synced from the server and sets `mode` again.
+    async fn restore_colors(&self, mode: &ModeData) -> OpenRgbResult<()> {
+        let colors = &self.controller.data().colors;
+        let start = self.offset().min(colors.len());
+        let end = (self.offset() + self.num_leds()).min(colors.len());
+        let colors = fit_colors(&colors[start..end], self.num_leds());
+        // restore the mode even if restoring the colors fails
+        let sent = self
+            .controller
+            .send_colors(LedTarget::Zone(self.zone_id), &colors, ColorOrigin::Device)
+            .await;
+        let restored = self.controller.restore_mode(mode).await;
+        sent.and(restored)
+    }
+
+    /// Turns off the LEDs of this zone, leaving the other zones of the controller as they are.
+    ///
+    /// This requires a mode with per-LED colors. If the active mode does not have per-LED colors,
+    /// the controller is first switched to a controllable mode, see `Controller::set_controllable_mode()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the controller has no mode with per-LED colors.
+    pub async fn disable(&self) -> OpenRgbResult<()> {
+        let per_led = |m: &ModeData| m.flags.contains(ModeFlag::HasPerLEDColor);
+        if !self.controller.active_mode().is_ok_and(per_led)
+            && !self.controller.controllable_mode_active()
+        {
+            if !self.controller.data().modes.iter().any(per_led) {
+                return Err(OpenRgbError::CommandError(format!(
+                    "Controller {} has no mode with per-LED colors",
+                    self.controller.name()
+                )));
+            }
+            self.controller.set_controllable_mode().await?;
+        }
+        self.controller
+            .set_zone_leds(self.zone_id, vec![Color::default(); self.num_leds()])
+            .await
+    }
+
+    /// Sets the speed of the active mode for this zone.
+    ///
+    /// OpenRGB applies modes to the whole controller, so this is only supported if this zone is the only zone of its controller,
+    /// which is how OpenRGB exposes devices with independent zones. The cached controller data is not synced.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the controller has other zones, or the active mode does not have `ModeFlag::HasSpeed`.
+    pub async fn set_active_mode_speed(&self, speed: u32) -> OpenRgbResult<()> {
+        self.update_active_mode(ModeFlag::HasSpeed, "speed", |m| m.set_speed(speed))
+            .await
+    }
+
+    /// Sets the direction of the active mode for this zone.
+    ///
+    /// Like `set_active_mode_speed()`, this is only supported if this zone is the only zone of its controller.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the controller has other zones, or the active mode does not support `direction`.
+    pub async fn set_active_mode_direction(&self, direction: Direction) -> OpenRgbResult<()> {
+        self.update_active_mode(direction.required_flag(), "direction", |m| {
+            m.set_direction(direction)
+        })
+        .await
+    }
+
+    /// Applies `f` to a copy of the active mode and sends it, if the mode only affects this zone.
+    async fn update_active_mode(
+        &self,
+        flag: ModeFlag,
+        setting: &str,
+        f: impl FnOnce(&mut ModeData),
+    ) -> OpenRgbResult<()> {
+        let num_zones = self.controller.data().zones.len();
+        if num_zones != 1 {
+            return Err(OpenRgbError::CommandError(format!(
+                "Cannot set {setting} for zone {} only, modes apply to all {num_zones} zones of controller {}",
+                self.zone_id,
+                self.controller.name()
+            )));
+        }
+        let active_mode = self.controller.active_mode()?;
+        if !active_mode.flags.contains(flag) {
+            return Err(OpenRgbError::CommandError(format!(
+                "Controller {} mode {} does not support setting {setting}",
+                self.controller.name(),
+                active_mode.name
+            )));
+        }
+        let mut mode = active_mode.clone();
+        f(&mut mode);
+        self.controller.mode_changed();
+        self.controller
+            .proto()
+            .update_mode(self.controller.id() as u32, &mode)
+            .await
+    }
+
     /// Sets the LEDs in this zone to the given colors.
     pub async fn set_leds(&self, colors: impl IntoIterator<Item = Color>) -> OpenRgbResult<()> {
         let color_v = colors.into_iter().collect::<Vec<_>>();
@@ -129,13 +479,30 @@ impl<'a> Zone<'a> {
     }
 
     /// Adds a segment to this zone.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error without contacting the server if the segment does not fit in this zone,
+    /// or if `name` is too long to be sent.
     pub async fn add_segment(
         &self,
         name: impl Into<String>,
         start_idx: usize,
         led_count: usize,
     ) -> OpenRgbResult<()> {
-        if start_idx + led_count > self.num_leds() {
+        let name = name.into();
+        // strings are sent with a u16 length that includes the null terminator
+        if name.len() >= u16::MAX as usize {
+            return Err(OpenRgbError::CommandError(format!(
+                "Segment name is {} bytes long, but can be at most {} bytes",
+                name.len(),
+                u16::MAX - 1
+            )));
+        }
+        if start_idx
+            .checked_add(led_count)
+            .is_none_or(|end| end > self.num_leds())
+        {
             return Err(OpenRgbError::CommandError(format!(
                 "Segment start index {} + count {} exceeds zone LED count {}",
                 start_idx,
@@ -144,7 +511,7 @@ impl<'a> Zone<'a> {
             )));
         }
 
-        let data = SegmentData::new(name.into(), start_idx as u32, led_count as u32);
+        let data = SegmentData::new(name, start_idx as u32, led_count as u32);
         self.controller
             .proto()
             .add_segment(self.controller.id() as u32, self.zone_id as u32, &data)
@@ -169,3 +536,381 @@ impl<'a> Zone<'a> {
             .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use array2d::Array2D;
+
+    use crate::data::{ModeFlag, ProtocolOption, SegmentData, ZoneType};
+    use crate::protocol::mock::{MockServer, controller_fixture, mode_fixture};
+    use crate::{Color, OpenRgbResult, PacketId};
+
+    #[tokio::test]
+    async fn test_matrix() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Keyboard", &[2, 4]);
+        let grid = Array2D::from_row_major(&[0, 1, 2, 3], 2, 2).unwrap();
+        data.zones[1].zone_type = ZoneType::Matrix;
+        data.zones[1].matrix = Some(grid.clone());
+        let mock = MockServer::start(5, vec![data]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        assert!(controller.get_zone(0)?.matrix().is_err());
+        assert_eq!(controller.get_zone(1)?.matrix()?, &grid);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_matrix_iter() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Keyboard", &[2, 3]);
+        let grid = Array2D::from_row_major(&[0, u32::MAX, 1, 2], 2, 2).unwrap();
+        data.zones[1].zone_type = ZoneType::Matrix;
+        data.zones[1].matrix = Some(grid);
+        let mock = MockServer::start(5, vec![data]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        assert_eq!(controller.get_zone(0)?.matrix_iter().count(), 0);
+        let cells = controller.get_zone(1)?.matrix_iter().collect::<Vec<_>>();
+        assert_eq!(
+            cells,
+            vec![
+                (0, 0, Some(2)),
+                (0, 1, None),
+                (1, 0, Some(3)),
+                (1, 1, Some(4))
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_matrix_layout() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Keyboard", &[6, 2, 6]);
+        let grid = Array2D::from_row_major(&[0, 1, 2, 3, 4, 5], 2, 3).unwrap();
+        for zone_id in [0, 2] {
+            data.zones[zone_id].zone_type = ZoneType::Matrix;
+            data.zones[zone_id].matrix = Some(grid.clone());
+        }
+        let mock = MockServer::start(5, vec![data]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        let layout = controller.matrix_layout();
+        assert_eq!(layout.len(), 2);
+        let (first, second) = (layout[0], layout[1]);
+        assert_eq!((first.zone_id(), second.zone_id()), (0, 2));
+        assert_eq!((first.height(), first.width()), (2, 3));
+        assert_eq!((second.height(), second.width()), (2, 3));
+        assert_eq!((first.row_offset(), first.column_offset()), (0, 0));
+        // the second matrix starts below the first one, so the cells do not overlap
+        assert!(second.row_offset() >= first.row_offset() + first.height());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_update_matrix_sparse() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Keyboard", &[2, 4]);
+        let grid = Array2D::from_row_major(&[0, u32::MAX, 1, 2, u32::MAX, 3], 2, 3).unwrap();
+        data.zones[1].zone_type = ZoneType::Matrix;
+        data.zones[1].matrix = Some(grid);
+        let mock = MockServer::start(5, vec![data]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+        let zone = controller.get_zone(1)?;
+
+        let red = Color::new(255, 0, 0);
+        let blue = Color::new(0, 0, 255);
+        zone.update_matrix_sparse(&[((0, 2), red), ((1, 2), blue)])
+            .await?;
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateSingleLed, 2)
+            .await;
+        let sent = packets
+            .iter()
+            .map(|p| {
+                let mut msg = p.message(5);
+                Ok((msg.read_u32()?, msg.read_value::<Color>()?))
+            })
+            .collect::<OpenRgbResult<Vec<_>>>()?;
+        // zone 1 starts at LED 2
+        assert_eq!(sent, vec![(3, red), (5, blue)]);
+
+        assert!(zone.update_matrix_sparse(&[((0, 1), red)]).await.is_err());
+        assert!(zone.update_matrix_sparse(&[((2, 0), red)]).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_matrix_colors() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Keyboard", &[1, 3]);
+        let grid = Array2D::from_row_major(&[0, u32::MAX, 1, 2], 2, 2).unwrap();
+        data.zones[1].zone_type = ZoneType::Matrix;
+        data.zones[1].matrix = Some(grid);
+        let colors = (0..4).map(|i| Color::new(i, i, i)).collect::<Vec<_>>();
+        data.colors = colors.clone();
+        let mock = MockServer::start(5, vec![data]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        let expected = Array2D::from_row_major(
+            &[Some(colors[1]), None, Some(colors[2]), Some(colors[3])],
+            2,
+            2,
+        )
+        .unwrap();
+        assert_eq!(controller.get_zone(1)?.matrix_colors().await?, expected);
+        assert!(controller.get_zone(0)?.matrix_colors().await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_matrix_colors_short_buffer() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Keyboard", &[2]);
+        data.zones[0].zone_type = ZoneType::Matrix;
+        data.zones[0].matrix = Some(Array2D::from_row_major(&[0, 1], 1, 2).unwrap());
+        data.colors = vec![Color::new(1, 2, 3)];
+        let mock = MockServer::start(5, vec![data]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        let grid = controller.get_zone(0)?.matrix_colors().await?;
+        assert_eq!(grid.as_row_major(), vec![Some(Color::new(1, 2, 3)), None]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_active_mode_speed() -> OpenRgbResult<()> {
+        let mut fan = controller_fixture("Fan", &[4]);
+        fan.modes[0].flags |= ModeFlag::HasSpeed;
+        fan.modes[0].speed_max = 10;
+        let mut strip = controller_fixture("Strip", &[2, 3]);
+        strip.modes[0].flags |= ModeFlag::HasSpeed;
+        let mock = MockServer::start(5, vec![fan, strip]).await;
+        let client = mock.client().await;
+
+        let strip = client.get_controller(1).await?;
+        let err = strip
+            .get_zone(0)?
+            .set_active_mode_speed(5)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("all 2 zones"), "{err}");
+
+        let fan = client.get_controller(0).await?;
+        fan.get_zone(0)?.set_active_mode_speed(5).await?;
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateMode, 1)
+            .await;
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].device_id, 0);
+        let fan = client.get_controller(0).await?;
+        assert_eq!(fan.active_mode()?.speed, 5);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fit_colors() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[3])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+        let zone = controller.get_zone(0)?;
+
+        let red = Color::new(255, 0, 0);
+        let black = Color::default();
+        assert_eq!(zone.fit_colors(&[red]), vec![red, black, black]);
+        assert_eq!(zone.fit_colors(&[red; 3]), vec![red; 3]);
+        assert_eq!(zone.fit_colors(&[red; 5]), vec![red; 3]);
+        assert_eq!(zone.fit_colors(&[]), vec![black; 3]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_has_segments() -> OpenRgbResult<()> {
+        let fixture = || {
+            let mut data = controller_fixture("Strip", &[4, 2]);
+            data.zones[0].segments = ProtocolOption::Some(vec![SegmentData::new("Left", 0, 2)]);
+            data
+        };
+
+        let mock = MockServer::start(5, vec![fixture()]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+        assert!(controller.supports_segments());
+        assert!(controller.get_zone(0)?.has_segments());
+        assert!(!controller.get_zone(1)?.has_segments());
+
+        let mock = MockServer::start(3, vec![fixture()]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+        assert!(!controller.supports_segments());
+        assert!(!controller.get_zone(0)?.has_segments());
+        assert!(!controller.get_zone(1)?.has_segments());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_segment_validation() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[4])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+        let zone = controller.get_zone(0)?;
+
+        assert!(zone.add_segment("Too long", 2, 3).await.is_err());
+        assert!(zone.add_segment("Overflow", usize::MAX, 1).await.is_err());
+        let name = "a".repeat(u16::MAX as usize);
+        assert!(zone.add_segment(name, 0, 1).await.is_err());
+        assert!(
+            mock.packets_of(PacketId::RGBControllerAddSegment)
+                .is_empty()
+        );
+
+        zone.add_segment("Fits", 2, 2).await?;
+        mock.wait_for_packets_of(PacketId::RGBControllerAddSegment, 1)
+            .await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resize_bounds() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[3, 10]);
+        data.zones[1].leds_min = 0;
+        data.zones[1].leds_max = 60;
+        let mock = MockServer::start(5, vec![data]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        let fixed = controller.get_zone(0)?;
+        assert_eq!(fixed.resize_bounds(), (3, 3));
+        assert!(!fixed.is_resizable());
+        let strip = controller.get_zone(1)?;
+        assert_eq!(strip.resize_bounds(), (0, 60));
+        assert!(strip.is_resizable());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_disable() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2, 3])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        controller.get_zone(1)?.disable().await?;
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateZoneLeds, 1)
+            .await;
+        let mut msg = packets[0].message(5);
+        let _data_size = msg.read_u32()?;
+        assert_eq!(msg.read_u32()?, 1);
+        assert_eq!(msg.read_value::<Vec<Color>>()?, vec![Color::default(); 3]);
+        // the active "Direct" mode already has per-LED colors
+        assert!(
+            mock.packets_of(PacketId::RGBControllerUpdateMode)
+                .is_empty()
+        );
+        assert!(
+            mock.packets_of(PacketId::RGBControllerUpdateLeds)
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_disable_switches_mode() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[2, 3]);
+        data.active_mode = 1;
+        let mut no_per_led = controller_fixture("Fixed", &[2]);
+        no_per_led.modes = vec![mode_fixture("Static", ModeFlag::HasModeSpecificColor)];
+        let mock = MockServer::start(5, vec![data, no_per_led]).await;
+        let client = mock.client().await;
+
+        let controller = client.get_controller(0).await?;
+        controller.get_zone(0)?.disable().await?;
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateMode, 1)
+            .await;
+        let mut msg = packets[0].message(5);
+        let _data_size = msg.read_u32()?;
+        assert_eq!(msg.read_u32()?, 0);
+        // the preferred controllable mode is Direct
+        let mode = msg.read_value::<crate::ModeData>()?;
+        assert_eq!(mode.name, "Direct");
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateZoneLeds, 1)
+            .await;
+
+        // the controllable mode is still set, so it is not sent again
+        controller.get_zone(1)?.disable().await?;
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateZoneLeds, 2)
+            .await;
+        assert_eq!(mock.packets_of(PacketId::RGBControllerUpdateMode).len(), 1);
+
+        let controller = client.get_controller(1).await?;
+        assert!(controller.get_zone(0)?.disable().await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cycle_palette() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2, 3])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+        let zone = controller.get_zone(1)?;
+        let (r, g) = (Color::new(255, 0, 0), Color::new(0, 255, 0));
+
+        assert!(zone.cycle_palette(&[], Duration::ZERO, 3).await.is_err());
+        zone.cycle_palette(&[r, g], Duration::ZERO, 3).await?;
+        // restoring sends the colors of the zone last
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateZoneLeds, 4)
+            .await;
+        let frames = packets[..3]
+            .iter()
+            .map(|p| {
+                let mut msg = p.message(5);
+                let _data_size = msg.read_u32()?;
+                assert_eq!(msg.read_u32()?, 1);
+                msg.read_value::<Vec<Color>>()
+            })
+            .collect::<OpenRgbResult<Vec<_>>>()?;
+        assert_eq!(frames, vec![vec![r, g, r], vec![g, r, g], vec![r, g, r]]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cycle_palette_restores_on_error() -> OpenRgbResult<()> {
+        let mut data = controller_fixture("Strip", &[2, 3]);
+        data.active_mode = 1;
+        let mock = MockServer::start(5, vec![data]).await;
+        let client = mock.client().await;
+        let mut controller = client.get_controller(0).await?;
+        // the cached mode stays Static, so the first frame fails
+        controller.set_strict_addressable_mode(true);
+        let zone = controller.get_zone(1)?;
+
+        let r = Color::new(255, 0, 0);
+        assert!(zone.cycle_palette(&[r], Duration::ZERO, 3).await.is_err());
+        mock.wait_for_packets_of(PacketId::RGBControllerUpdateMode, 2)
+            .await;
+        assert_eq!(client.get_controller(0).await?.data().active_mode, 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_update_with() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[2, 3])]).await;
+        let controller = mock.client().await.get_controller(0).await?;
+
+        controller
+            .get_zone(1)?
+            .update_with(|i, n| {
+                let v = (i * 255 / (n - 1)) as u8;
+                Color::new(v, v, v)
+            })
+            .await?;
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateZoneLeds, 1)
+            .await;
+        let mut msg = packets[0].message(5);
+        let _data_size = msg.read_u32()?;
+        assert_eq!(msg.read_u32()?, 1);
+        let colors = msg.read_value::<Vec<Color>>()?;
+        assert_eq!(
+            colors,
+            vec![
+                Color::new(0, 0, 0),
+                Color::new(127, 127, 127),
+                Color::new(255, 255, 255)
+            ]
+        );
+        Ok(())
+    }
+}
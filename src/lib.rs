@@ -5,20 +5,20 @@
 //! # Example
 //!
 //! ```no_run
-//! use openrgb::OpenRGB;
+//! use openrgb::OpenRgbClient;
 //! use std::error::Error;
 //!
 //! #[tokio::main]
 //! async fn main() -> Result<(), Box<dyn Error>> {
 //!
 //!     // connect to default server at localhost
-//!     let client = OpenRGB::connect().await?;
+//!     let client = OpenRgbClient::connect().await?;
 //!
 //!     Ok(())
 //! }
 //! ```
 //!
-//! See [examples](https://github.com/nicoulaj/openrgb-rs/tree/master/examples), and [OpenRGB] for client API.
+//! See [examples](https://github.com/nicoulaj/openrgb-rs/tree/master/examples), and [OpenRgbClient] for client API.
 
 #![warn(missing_docs)]
 #![deny(rustdoc::broken_intra_doc_links)]
@@ -1,4 +1,4 @@
-use rgb::RGB8;
+use rgb::{RGB8, RGBA8};
 
 use crate::OpenRgbResult;
 use crate::protocol::{DeserFromBuf, ReceivedMessage, SerToBuf, WriteMessage};
@@ -16,6 +16,15 @@ impl DeserFromBuf for Color {
         let _ = buf.read_u8()?; // Skip the alpha channel
         Ok(Color { r, g, b })
     }
+
+    /// Reads all colors from one slice, since color arrays can be large.
+    fn deserialize_n(buf: &mut ReceivedMessage<'_>, n: usize) -> OpenRgbResult<Vec<Self>> {
+        let bytes = buf.read_slice(n * 4)?;
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|c| Color::new(c[0], c[1], c[2]))
+            .collect())
+    }
 }
 
 impl SerToBuf for Color {
@@ -28,47 +37,158 @@ impl SerToBuf for Color {
     }
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use std::error::Error;
+/// RGB controller color including the fourth byte, aliased to [rgb] crate's [RGBA8] type.
+///
+/// OpenRGB sends every color as 4 bytes, of which the last one is currently unused and always `0`.
+/// [Color] discards this byte, while `ColorA` keeps it in its `a` channel.
+/// This is useful for plugins that use the fourth byte as brightness/alpha.
+pub type ColorA = RGBA8;
 
-//     use tokio_test::io::Builder;
+impl DeserFromBuf for ColorA {
+    fn deserialize(buf: &mut ReceivedMessage<'_>) -> OpenRgbResult<Self> {
+        let r = buf.read_u8()?;
+        let g = buf.read_u8()?;
+        let b = buf.read_u8()?;
+        let a = buf.read_u8()?;
+        Ok(ColorA { r, g, b, a })
+    }
+}
 
-//     use crate::protocol::data::Color;
-//     use crate::protocol::tests::setup;
+impl SerToBuf for ColorA {
+    fn serialize(&self, buf: &mut WriteMessage) -> OpenRgbResult<()> {
+        buf.write_u8(self.r);
+        buf.write_u8(self.g);
+        buf.write_u8(self.b);
+        buf.write_u8(self.a);
+        Ok(())
+    }
+}
 
-//     #[tokio::test]
-//     async fn test_read_001() -> Result<(), Box<dyn Error>> {
-//         setup()?;
+/// Returns the index of the color in `palette` closest to `color`, or `None` if `palette` is empty.
+///
+/// Distance is the squared Euclidean distance in RGB space. Ties are broken by the first occurrence in `palette`.
+pub fn nearest_index_in_palette(color: Color, palette: &[Color]) -> Option<usize> {
+    let distance = |other: &Color| {
+        let dr = color.r as i32 - other.r as i32;
+        let dg = color.g as i32 - other.g as i32;
+        let db = color.b as i32 - other.b as i32;
+        dr * dr + dg * dg + db * db
+    };
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, c)| distance(c))
+        .map(|(i, _)| i)
+}
 
-//         let mut stream = Builder::new().read(&[37_u8, 54_u8, 126_u8, 0_u8]).build();
+/// Returns the color in `palette` closest to `color`, or `None` if `palette` is empty.
+///
+/// This can be used to map arbitrary colors to the colors of a mode, see `ModeData::colors`.
+/// See [nearest_index_in_palette] for how the closest color is chosen.
+pub fn nearest_in_palette(color: Color, palette: &[Color]) -> Option<Color> {
+    nearest_index_in_palette(color, palette).map(|i| palette[i])
+}
 
-//         assert_eq!(
-//             stream.read_value::<Color>().await?,
-//             Color {
-//                 r: 37,
-//                 g: 54,
-//                 b: 126
-//             }
-//         );
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
 
-//         Ok(())
-//     }
+    use crate::WriteMessage;
+    use crate::protocol::data::{Color, ColorA, nearest_in_palette, nearest_index_in_palette};
 
-//     #[tokio::test]
-//     async fn test_write_001() -> Result<(), Box<dyn Error>> {
-//         setup()?;
+    #[test]
+    fn test_read_001() -> Result<(), Box<dyn Error>> {
+        let mut buf = WriteMessage::new(crate::DEFAULT_PROTOCOL);
+        buf.write_slice(&[37, 54, 126, 0]);
+        let mut msg = buf.to_received_msg();
 
-//         let mut stream = Builder::new().write(&[37_u8, 54_u8, 126_u8, 0_u8]).build();
+        assert_eq!(msg.read_value::<Color>()?, Color::new(37, 54, 126));
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_001() -> Result<(), Box<dyn Error>> {
+        let mut buf = WriteMessage::new(crate::DEFAULT_PROTOCOL);
+        buf.write_value(&Color::new(37, 54, 126))?;
+
+        assert_eq!(buf.bytes(), &[37, 54, 126, 0]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_many() -> Result<(), Box<dyn Error>> {
+        let colors = (0..1000)
+            .map(|i: u32| Color::new(i as u8, (i >> 2) as u8, (i * 7) as u8))
+            .collect::<Vec<_>>();
+        let mut buf = WriteMessage::new(crate::DEFAULT_PROTOCOL);
+        buf.write_value(&colors)?;
+
+        let mut msg = buf.to_received_msg();
+        assert_eq!(msg.read_value::<Vec<Color>>()?, colors);
+        assert_eq!(msg.remaining(), 0);
+
+        // same result as reading the colors one by one
+        let mut msg = buf.to_received_msg();
+        let len = msg.read_u16()? as usize;
+        let one_by_one = (0..len)
+            .map(|_| msg.read_value::<Color>())
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(one_by_one, colors);
+
+        let mut short = WriteMessage::new(crate::DEFAULT_PROTOCOL);
+        short.write_u16(2);
+        short.write_slice(&[1, 2, 3, 0, 4, 5]);
+        assert!(short.to_received_msg().read_value::<Vec<Color>>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_alpha_round_trip() -> Result<(), Box<dyn Error>> {
+        let color = ColorA::new(37, 54, 126, 200);
+        let mut buf = WriteMessage::new(crate::DEFAULT_PROTOCOL);
+        buf.write_value(&color)?;
+        assert_eq!(buf.bytes(), &[37, 54, 126, 200]);
+
+        let mut msg = buf.to_received_msg();
+        assert_eq!(msg.read_value::<ColorA>()?, color);
+        Ok(())
+    }
 
-//         stream
-//             .write_value(&Color {
-//                 r: 37,
-//                 g: 54,
-//                 b: 126,
-//             })
-//             .await?;
+    #[test]
+    fn test_alpha_discarded_by_color() -> Result<(), Box<dyn Error>> {
+        let mut buf = WriteMessage::new(crate::DEFAULT_PROTOCOL);
+        buf.write_value(&ColorA::new(37, 54, 126, 200))?;
+        let mut msg = buf.to_received_msg();
 
-//         Ok(())
-//     }
-// }
+        assert_eq!(msg.read_value::<Color>()?, Color::new(37, 54, 126));
+        Ok(())
+    }
+
+    #[test]
+    fn test_nearest_in_palette() {
+        let palette = [
+            Color::new(255, 0, 0),
+            Color::new(0, 255, 0),
+            Color::new(0, 0, 255),
+            Color::new(0, 255, 0),
+        ];
+        assert_eq!(
+            nearest_in_palette(Color::new(200, 30, 40), &palette),
+            Some(Color::new(255, 0, 0))
+        );
+        assert_eq!(
+            nearest_index_in_palette(Color::new(10, 20, 250), &palette),
+            Some(2)
+        );
+        // equally close to red and green, and green appears twice
+        assert_eq!(
+            nearest_index_in_palette(Color::new(128, 128, 0), &palette),
+            Some(0)
+        );
+        assert_eq!(
+            nearest_index_in_palette(Color::new(0, 200, 0), &palette),
+            Some(1)
+        );
+        assert_eq!(nearest_in_palette(Color::new(1, 2, 3), &[]), None);
+    }
+}
@@ -6,14 +6,18 @@ impl<T> DeserFromBuf for FlagSet<T>
 where
     T: flagset::Flags<Type = u32>,
 {
+    /// Unknown bits are dropped, since newer OpenRGB versions may define flags this crate does not know yet.
     fn deserialize(buf: &mut crate::ReceivedMessage<'_>) -> crate::OpenRgbResult<Self> {
         let value = buf.read_u32()?;
-        FlagSet::<T>::new(value).map_err(|e| {
-            crate::OpenRgbError::ProtocolError(format!(
-                "Received invalid flag: {value:#032b} ({e}) (for {})",
+        let flags = FlagSet::<T>::new_truncated(value);
+        if flags.bits() != value {
+            tracing::warn!(
+                "Received unknown flag bits: {:#034b} (for {})",
+                value & !flags.bits(),
                 std::any::type_name::<T>()
-            ))
-        })
+            );
+        }
+        Ok(flags)
     }
 }
 
@@ -26,3 +30,22 @@ where
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use flagset::FlagSet;
+
+    use crate::data::ControllerFlags;
+    use crate::{OpenRgbResult, WriteMessage};
+
+    #[test]
+    fn test_unknown_bits_dropped() -> OpenRgbResult<()> {
+        let mut buf = WriteMessage::new(5);
+        buf.write_u32(1 << 0 | 1 << 2 | 1 << 20);
+        let flags = buf
+            .to_received_msg()
+            .read_value::<FlagSet<ControllerFlags>>()?;
+        assert_eq!(flags, ControllerFlags::IsLocal | ControllerFlags::IsVirtual);
+        Ok(())
+    }
+}
@@ -3,15 +3,28 @@ use std::io::Read;
 use crate::protocol::{DeserFromBuf, ReceivedMessage, SerToBuf, WriteMessage};
 use crate::{OpenRgbError, OpenRgbResult};
 
+/// Strings are a `u16` length followed by that many bytes, which normally includes a null terminator.
+///
+/// An empty string can be sent as length 0 without terminator, or length 1 with only the terminator;
+/// both decode to `""`.
 impl DeserFromBuf for String {
     fn deserialize(buf: &mut ReceivedMessage<'_>) -> OpenRgbResult<Self>
     where
         Self: Sized,
     {
         let len = buf.read_u16()? as usize;
+        if len > buf.remaining() {
+            return Err(OpenRgbError::ProtocolError(format!(
+                "String length {len} exceeds the remaining {} bytes of the message",
+                buf.remaining()
+            )));
+        }
         let mut bytes = vec![0u8; len];
         buf.read_exact(&mut bytes)?;
-        bytes.pop(); // null byte?
+        // length includes the null terminator, but not every sender includes one
+        if bytes.last() == Some(&b'\0') {
+            bytes.pop();
+        }
         String::from_utf8(bytes).map_err(|e| {
             OpenRgbError::ProtocolError(format!("Failed decoding string as UTF-8: {e}"))
         })
@@ -40,18 +53,31 @@ pub struct RawString<'a>(pub &'a str);
 
 impl SerToBuf for RawString<'_> {
     fn serialize(&self, buf: &mut WriteMessage) -> OpenRgbResult<()> {
-        buf.write_slice(self.0.as_bytes());
+        buf.write_value(&RawStringNoNull(self.0))?;
         buf.write_u8(b'\0');
         Ok(())
     }
 }
 
+/// A raw string that is serialized without length and without null terminator.
+///
+/// This is for plugins and packets that expect the bare bytes of a string.
+/// Use [`RawString`] if the string should be null terminated.
+pub struct RawStringNoNull<'a>(pub &'a str);
+
+impl SerToBuf for RawStringNoNull<'_> {
+    fn serialize(&self, buf: &mut WriteMessage) -> OpenRgbResult<()> {
+        buf.write_slice(self.0.as_bytes());
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::error::Error;
 
     use crate::WriteMessage;
-    use crate::protocol::data::implement::string::RawString;
+    use crate::protocol::data::implement::string::{RawString, RawStringNoNull};
 
     #[tokio::test]
     async fn test_read_001() -> Result<(), Box<dyn Error>> {
@@ -73,4 +99,63 @@ mod tests {
         assert_eq!(msg.read_value::<String>()?, "test".to_string());
         Ok(())
     }
+
+    #[test]
+    fn test_read_empty() -> Result<(), Box<dyn Error>> {
+        let mut buf = WriteMessage::new(crate::DEFAULT_PROTOCOL);
+        let mut msg = buf
+            .push_value(&0_u16)?
+            .push_value(&1_u16)?
+            .push_value(&0_u8)?
+            .to_received_msg();
+
+        assert_eq!(msg.read_value::<String>()?, "");
+        assert_eq!(msg.read_value::<String>()?, "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_length_exceeds_buffer() -> Result<(), Box<dyn Error>> {
+        let mut buf = WriteMessage::new(crate::DEFAULT_PROTOCOL);
+        let mut msg = buf
+            .push_value(&10_u16)?
+            .push_value(&RawString("abc"))?
+            .to_received_msg();
+
+        let err = msg.read_value::<String>().unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("String length 10 exceeds the remaining 4 bytes"),
+            "{err}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_without_null() -> Result<(), Box<dyn Error>> {
+        let mut buf = WriteMessage::new(crate::DEFAULT_PROTOCOL);
+        let mut msg = buf
+            .push_value(&4_u16)?
+            .push_value(&RawStringNoNull("test"))?
+            .to_received_msg();
+
+        assert_eq!(msg.read_value::<String>()?, "test");
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_terminators() -> Result<(), Box<dyn Error>> {
+        let mut buf = WriteMessage::new(crate::DEFAULT_PROTOCOL);
+        buf.write_value(&"")?;
+        assert_eq!(buf.bytes(), &[1, 0, 0]);
+
+        let mut buf = WriteMessage::new(crate::DEFAULT_PROTOCOL);
+        buf.write_value(&RawString("ab"))?;
+        assert_eq!(buf.bytes(), b"ab\0");
+
+        let mut buf = WriteMessage::new(crate::DEFAULT_PROTOCOL);
+        buf.write_value(&RawStringNoNull("ab"))?;
+        assert_eq!(buf.bytes(), b"ab");
+        Ok(())
+    }
 }
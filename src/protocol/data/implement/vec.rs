@@ -7,11 +7,7 @@ impl<T: DeserFromBuf> DeserFromBuf for Vec<T> {
         Self: Sized,
     {
         let len = buf.read_u16()? as usize;
-        let mut vec = Vec::with_capacity(len);
-        for _ in 0..len {
-            vec.push(T::deserialize(buf)?);
-        }
-        Ok(vec)
+        T::deserialize_n(buf, len)
     }
 }
 
@@ -1,9 +1,11 @@
 use flagset::{FlagSet, flags};
 
-use crate::OpenRgbResult;
 use crate::data::ProtocolOption;
 use crate::protocol::data::{Color, DeviceType, Led, ModeData, ZoneData};
-use crate::protocol::{DeserFromBuf, ReceivedMessage};
+use crate::protocol::{
+    DataSizePrefix, DeserFromBuf, OpenRgbPacket, PacketId, ReceivedMessage, SerToBuf, WriteMessage,
+};
+use crate::{OpenRgbError, OpenRgbResult};
 
 flags! {
     /// RGB Controller flags.
@@ -36,6 +38,8 @@ pub struct ControllerData {
     pub name: String,
 
     /// Controller vendor.
+    ///
+    /// Minimum protocol version: 1, empty for older versions.
     pub vendor: String,
 
     /// Controller description.
@@ -78,27 +82,74 @@ pub struct ControllerData {
     /* NOT IN PROTOCOL, BUT USEFUL */
     /// Id of this controller, which is the id used to make the request.
     pub id: u32,
-    /// Number of LEDs in this controller.
+    /// Protocol version this controller was read with.
     ///
-    /// Computed by adding up the zone's lengths.
-    pub num_leds: usize,
+    /// Fields that require a higher protocol version are `None`.
+    pub protocol_version: u32,
 }
 
 impl ControllerData {
+    /// Returns the number of LEDs in this controller.
+    ///
+    /// Computed by adding up the zone's lengths, which should equal the number of `leds`.
+    pub fn num_leds(&self) -> usize {
+        self.zones.iter().map(|z| z.leds_count as usize).sum()
+    }
+
+    /// Reads a controller from `bytes` in the format of a `RequestControllerData` response,
+    /// e.g. as written by [`ControllerData::to_bytes`].
+    ///
+    /// This allows persisting device descriptions and reloading them without a server.
+    /// `id` is not part of the data, so it is set to `u32::MAX`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `bytes` is not a valid controller for `protocol_version`,
+    /// or its data size does not match the length of `bytes`.
+    pub fn from_bytes(bytes: &[u8], protocol_version: u32) -> OpenRgbResult<Self> {
+        let mut buf = ReceivedMessage::new(bytes, protocol_version);
+        DataSizePrefix::of(PacketId::RequestControllerData).read(&mut buf)?;
+        let controller = buf.read_value::<Self>()?;
+        if buf.remaining() > 0 {
+            return Err(OpenRgbError::ProtocolError(format!(
+                "Controller data has {} trailing bytes",
+                buf.remaining()
+            )));
+        }
+        Ok(controller)
+    }
+
+    /// Writes this controller in the format of a `RequestControllerData` response for `protocol_version`.
+    ///
+    /// See [`ControllerData::from_bytes`] for the inverse.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if this controller cannot be represented in `protocol_version`.
+    pub fn to_bytes(&self, protocol_version: u32) -> OpenRgbResult<Vec<u8>> {
+        let mut buf = WriteMessage::new(protocol_version);
+        buf.write_value(&OpenRgbPacket::new(PacketId::RequestControllerData, self))?;
+        Ok(buf.bytes().to_vec())
+    }
+
     /// Returns the mode of this controller that is currently active.
     ///
-    /// Currently unsure if this can ever be `None`
+    /// Returns `None` if `active_mode` is negative or out of range, which buggy servers can report.
     pub fn active_mode(&self) -> Option<&ModeData> {
-        self.modes.get(self.active_mode as usize)
+        let index = usize::try_from(self.active_mode).ok()?;
+        self.modes.get(index)
     }
 }
 
 impl DeserFromBuf for ControllerData {
     fn deserialize(buf: &mut ReceivedMessage<'_>) -> OpenRgbResult<Self> {
-        let _data_size = buf.read_u32()?;
         let device_type = buf.read_value()?;
         let name = buf.read_value()?;
-        let vendor = buf.read_value()?;
+        // vendor was added in protocol version 1
+        let vendor = match buf.protocol_version() {
+            0 => String::new(),
+            _ => buf.read_value()?,
+        };
         let description = buf.read_value()?;
         let version = buf.read_value()?;
         let serial = buf.read_value()?;
@@ -112,10 +163,8 @@ impl DeserFromBuf for ControllerData {
         }
 
         let mut zones = buf.read_value::<Vec<ZoneData>>()?;
-        let mut num_leds = 0;
         for (idx, zone) in zones.iter_mut().enumerate() {
             zone.id = idx as u32;
-            num_leds += zone.leds_count as usize;
         }
 
         let leds = buf.read_value()?;
@@ -139,11 +188,38 @@ impl DeserFromBuf for ControllerData {
             led_alt_names,
             flags,
             id: u32::MAX,
-            num_leds,
+            protocol_version: buf.protocol_version(),
         })
     }
 }
 
+/// Writes the controller in the format of a `RequestControllerData` response, without the data size.
+///
+/// Use [`ControllerData::to_bytes`] for a complete response.
+impl SerToBuf for ControllerData {
+    fn serialize(&self, buf: &mut WriteMessage) -> OpenRgbResult<()> {
+        buf.push_value(&self.device_type)?.push_value(&self.name)?;
+        if buf.protocol_version() >= 1 {
+            buf.write_value(&self.vendor)?;
+        }
+        buf.push_value(&self.description)?
+            .push_value(&self.version)?
+            .push_value(&self.serial)?
+            .push_value(&self.location)?
+            .push_value(&(self.modes.len() as u16))?
+            .push_value(&self.active_mode)?;
+        for mode in &self.modes {
+            buf.write_value(mode)?;
+        }
+        buf.push_value(&self.zones)?
+            .push_value(&self.leds)?
+            .push_value(&self.colors)?
+            .push_value(&self.led_alt_names)?
+            .push_value(&self.flags)?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::error::Error;
@@ -151,46 +227,13 @@ mod tests {
     use crate::WriteMessage;
     use crate::data::ProtocolOption;
     use crate::protocol::data::ControllerData;
+    use crate::protocol::mock::{controller_fixture, thermaltake_riing};
+    use crate::protocol::{OpenRgbPacket, PacketId};
 
     #[test]
     fn test_read_001() -> Result<(), Box<dyn Error>> {
-        // this message is protocol version 3
-        let mut buf = WriteMessage::new(3);
-        buf.write_u32(760);
-        buf.write_slice(&[
-            3, 0, 0, 0, 18, 0, 84, 104, 101, 114, 109, 97, 108, 116, 97, 107, 101, 32, 82, 105,
-            105, 110, 103, 0, 12, 0, 84, 104, 101, 114, 109, 97, 108, 116, 97, 107, 101, 0, 25, 0,
-            84, 104, 101, 114, 109, 97, 108, 116, 97, 107, 101, 32, 82, 105, 105, 110, 103, 32, 68,
-            101, 118, 105, 99, 101, 0, 1, 0, 0, 1, 0, 0, 19, 0, 72, 73, 68, 58, 32, 47, 100, 101,
-            118, 47, 104, 105, 100, 114, 97, 119, 49, 48, 0, 8, 0, 0, 0, 0, 0, 7, 0, 68, 105, 114,
-            101, 99, 116, 0, 24, 0, 0, 0, 32, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 7, 0,
-            83, 116, 97, 116, 105, 99, 0, 25, 0, 0, 0, 64, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0,
-            0, 1, 0, 0, 0, 0, 0, 5, 0, 70, 108, 111, 119, 0, 0, 0, 0, 0, 1, 0, 0, 0, 3, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 9, 0, 83, 112, 101, 99, 116, 114, 117, 109, 0, 4, 0, 0, 0, 1,
-            0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 7, 0, 82, 105, 112, 112, 108, 101, 0,
-            8, 0, 0, 0, 33, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 6, 0, 66, 108, 105, 110,
-            107, 0, 12, 0, 0, 0, 33, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 6, 0, 80, 117,
-            108, 115, 101, 0, 16, 0, 0, 0, 33, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 5,
-            0, 87, 97, 118, 101, 0, 20, 0, 0, 0, 33, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0,
-            0, 5, 0, 16, 0, 82, 105, 105, 110, 103, 32, 67, 104, 97, 110, 110, 101, 108, 32, 49, 0,
-            1, 0, 0, 0, 0, 0, 0, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 16, 0, 82, 105, 105, 110, 103,
-            32, 67, 104, 97, 110, 110, 101, 108, 32, 50, 0, 1, 0, 0, 0, 0, 0, 0, 0, 20, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 16, 0, 82, 105, 105, 110, 103, 32, 67, 104, 97, 110, 110, 101, 108, 32,
-            51, 0, 1, 0, 0, 0, 0, 0, 0, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 16, 0, 82, 105, 105, 110,
-            103, 32, 67, 104, 97, 110, 110, 101, 108, 32, 52, 0, 1, 0, 0, 0, 0, 0, 0, 0, 20, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 16, 0, 82, 105, 105, 110, 103, 32, 67, 104, 97, 110, 110, 101,
-            108, 32, 53, 0, 1, 0, 0, 0, 0, 0, 0, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-        ]);
-        let mut msg = buf.to_received_msg();
-        let c_data = msg.read_value::<ControllerData>()?;
+        let buf = thermaltake_riing();
+        let c_data = ControllerData::from_bytes(buf.bytes(), 3)?;
 
         assert_eq!(c_data.name, "Thermaltake Riing".to_string());
         assert_eq!(c_data.vendor, "Thermaltake".to_string());
@@ -208,4 +251,73 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_num_leds() -> Result<(), Box<dyn Error>> {
+        let controller = ControllerData::from_bytes(thermaltake_riing().bytes(), 3)?;
+        assert_eq!(controller.num_leds(), controller.leds.len());
+
+        let controller = controller_fixture("Strip", &[2, 3]);
+        assert_eq!(controller.num_leds(), 5);
+        assert_eq!(controller.num_leds(), controller.leds.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_active_mode() -> Result<(), Box<dyn Error>> {
+        let mut c_data = ControllerData::from_bytes(thermaltake_riing().bytes(), 3)?;
+        c_data.active_mode = 1;
+        assert_eq!(c_data.active_mode().map(|m| m.name()), Some("Static"));
+        c_data.active_mode = -1;
+        assert_eq!(c_data.active_mode(), None);
+        c_data.active_mode = 8;
+        assert_eq!(c_data.active_mode(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_round_trip() -> Result<(), Box<dyn Error>> {
+        let buf = thermaltake_riing();
+        let c_data = ControllerData::from_bytes(buf.bytes(), 3)?;
+
+        // the captured data size counts itself
+        let mut written = WriteMessage::new(3);
+        written.write_value(&OpenRgbPacket::new(
+            PacketId::RequestControllerData,
+            &c_data,
+        ))?;
+        assert_eq!(written.bytes(), buf.bytes());
+        assert_eq!(written.to_received_msg().read_u32()?, 760);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bytes_round_trip() -> Result<(), Box<dyn Error>> {
+        let buf = thermaltake_riing();
+        let c_data = ControllerData::from_bytes(buf.bytes(), 3)?;
+        assert_eq!(c_data.name, "Thermaltake Riing");
+
+        let bytes = c_data.to_bytes(3)?;
+        assert_eq!(bytes, buf.bytes());
+        assert_eq!(ControllerData::from_bytes(&bytes, 3)?, c_data);
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(ControllerData::from_bytes(&trailing, 3).is_err());
+        assert!(ControllerData::from_bytes(&bytes[..bytes.len() - 1], 3).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_protocol_version() -> Result<(), Box<dyn Error>> {
+        let fixture = controller_fixture("Strip", &[2]);
+        for version in [4, 5] {
+            let bytes = fixture.to_bytes(version)?;
+            let c_data = ControllerData::from_bytes(&bytes, version)?;
+            assert_eq!(c_data.protocol_version, version);
+            assert_eq!(c_data.flags.value().is_some(), version >= 5);
+            assert_eq!(c_data.led_alt_names.value().is_some(), version >= 5);
+        }
+        Ok(())
+    }
 }
@@ -4,6 +4,7 @@ use crate::impl_enum_discriminant;
 ///
 /// See [Open SDK documentation](https://gitlab.com/CalcProgrammer1/OpenRGB/-/wikis/OpenRGB-SDK-Documentation) for more information.
 #[derive(Eq, PartialEq, Debug, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum DeviceType {
     /// Motherboard.
     Motherboard = 0,
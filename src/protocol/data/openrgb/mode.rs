@@ -68,6 +68,17 @@ pub enum Direction {
     Vertical = 5,
 }
 
+impl Direction {
+    /// Returns the mode flag that is required to use this direction.
+    pub fn required_flag(&self) -> ModeFlag {
+        match self {
+            Direction::Left | Direction::Right => ModeFlag::HasDirectionLR,
+            Direction::Up | Direction::Down => ModeFlag::HasDirectionUD,
+            Direction::Horizontal | Direction::Vertical => ModeFlag::HasDirectionHV,
+        }
+    }
+}
+
 impl_enum_discriminant!(
     Direction,
     Left: 0,
@@ -242,6 +253,15 @@ impl ModeData {
             .then_some(self.direction)
     }
 
+    /// Set the direction of this mode.
+    ///
+    /// The direction is only changed if the mode supports it, e.g. `Direction::Up` requires `ModeFlag::HasDirectionUD`.
+    pub fn set_direction(&mut self, d: Direction) {
+        if self.flags.contains(d.required_flag()) {
+            self.direction = d;
+        }
+    }
+
     /// Returns the color mode of this mode.
     pub fn color_mode(&self) -> ColorMode {
         self.color_mode
@@ -1,6 +1,7 @@
-use crate::{DeserFromBuf, ReceivedMessage};
+use crate::{DeserFromBuf, OpenRgbResult, ReceivedMessage, SerToBuf, WriteMessage};
 
 /// Data for OpenRGB plugins.
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct PluginData {
     /// Plugin name
     name: String,
@@ -15,6 +16,22 @@ pub struct PluginData {
 }
 
 impl PluginData {
+    #[cfg(test)]
+    pub(crate) fn new(name: impl Into<String>, index: u32, plugin_protocol_version: u32) -> Self {
+        Self {
+            name: name.into(),
+            description: String::new(),
+            version: "1.0".to_string(),
+            index,
+            plugin_protocol_version,
+        }
+    }
+
+    /// Returns true if the name of this plugin is `name`, ignoring case.
+    pub fn matches(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+
     /// Returns the name of this plugin.
     pub fn name(&self) -> &str {
         &self.name
@@ -57,3 +74,27 @@ impl DeserFromBuf for PluginData {
         })
     }
 }
+
+impl SerToBuf for PluginData {
+    fn serialize(&self, buf: &mut WriteMessage) -> OpenRgbResult<()> {
+        buf.push_value(&self.name)?
+            .push_value(&self.description)?
+            .push_value(&self.version)?
+            .push_value(&self.index)?
+            .push_value(&self.plugin_protocol_version)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PluginData;
+
+    #[test]
+    fn test_matches() {
+        let plugin = PluginData::new("Effects", 0, 1);
+        assert!(plugin.matches("Effects"));
+        assert!(plugin.matches("effects"));
+        assert!(!plugin.matches("Effect"));
+    }
+}
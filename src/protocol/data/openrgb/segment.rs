@@ -44,6 +44,18 @@ impl SegmentData {
         self.start_idx
     }
 
+    /// Returns the index of the first LED of this segment, relative to the start of its zone.
+    ///
+    /// This is the same as [`SegmentData::offset`].
+    pub fn start_led(&self) -> u32 {
+        self.start_idx
+    }
+
+    /// Returns the type of this segment.
+    pub fn segment_type(&self) -> ZoneType {
+        self.seg_type
+    }
+
     /// Returns the id of this segment.
     pub fn id(&self) -> usize {
         self.id
@@ -91,3 +103,24 @@ impl SerToBuf for SegmentData {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use crate::{DEFAULT_PROTOCOL, SegmentData, WriteMessage, ZoneType};
+
+    #[test]
+    fn test_accessors() -> Result<(), Box<dyn Error>> {
+        let mut buf = WriteMessage::new(DEFAULT_PROTOCOL);
+        buf.write_value(&SegmentData::new("Top", 4, 8))?;
+        let segment = buf.to_received_msg().read_value::<SegmentData>()?;
+
+        assert_eq!(segment.name(), "Top");
+        assert_eq!(segment.start_led(), 4);
+        assert_eq!(segment.offset(), 4);
+        assert_eq!(segment.led_count(), 8);
+        assert_eq!(segment.segment_type(), ZoneType::Linear);
+        Ok(())
+    }
+}
@@ -2,7 +2,7 @@ use array2d::Array2D;
 use flagset::{FlagSet, flags};
 
 use crate::protocol::data::ProtocolOption;
-use crate::protocol::{DeserFromBuf, ReceivedMessage};
+use crate::protocol::{DeserFromBuf, ReceivedMessage, SerToBuf, WriteMessage};
 use crate::{OpenRgbResult, impl_enum_discriminant};
 
 use super::SegmentData;
@@ -11,6 +11,7 @@ use super::SegmentData;
 ///
 /// See [Open SDK documentation](https://gitlab.com/CalcProgrammer1/OpenRGB/-/wikis/OpenRGB-SDK-Documentation#zone-data) for more information.
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ZoneType {
     /// Single zone.
     Single = 0,
@@ -119,6 +120,31 @@ impl DeserFromBuf for ZoneData {
     }
 }
 
+impl SerToBuf for ZoneData {
+    fn serialize(&self, buf: &mut WriteMessage) -> OpenRgbResult<()> {
+        buf.push_value(&self.name)?
+            .push_value(&self.zone_type)?
+            .push_value(&self.leds_min)?
+            .push_value(&self.leds_max)?
+            .push_value(&self.leds_count)?;
+        match &self.matrix {
+            None => buf.write_u16(0),
+            Some(matrix) => {
+                // matrix length is in bytes, including height and width
+                let matrix_len = (2 + matrix.num_elements()) * size_of::<u32>();
+                buf.write_u16(matrix_len as u16);
+                buf.write_u32(matrix.num_rows() as u32);
+                buf.write_u32(matrix.num_columns() as u32);
+                for value in matrix.elements_row_major_iter() {
+                    buf.write_u32(*value);
+                }
+            }
+        }
+        buf.push_value(&self.segments)?.push_value(&self.flags)?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::error::Error;
@@ -49,6 +49,11 @@ impl<const VER: usize, T> ProtocolOption<VER, T> {
         }
     }
 
+    /// Returns true if the value is supported by the current protocol version.
+    pub fn is_supported(&self) -> bool {
+        matches!(self, Self::Some(_))
+    }
+
     /// Returns `Some(&T)` if the value is supported by the current protocol version, otherwise `None`.
     pub fn value(&self) -> Option<&T> {
         match self {
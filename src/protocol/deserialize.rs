@@ -44,6 +44,12 @@ impl WriteMessage {
         }
     }
 
+    /// Clears this message, keeping its allocated capacity, so it can be reused for another message.
+    pub fn clear(&mut self, protocol_version: u32) {
+        self.protocol_version = protocol_version;
+        self.buf.clear();
+    }
+
     pub fn len(&self) -> usize {
         self.buf.len()
     }
@@ -68,6 +74,11 @@ impl WriteMessage {
         let _ = self.write(&value.to_le_bytes());
     }
 
+    /// Overwrites the 4 bytes at `offset` with `value`, e.g. to fill in a length after writing the data.
+    pub fn set_u32_at(&mut self, offset: usize, value: u32) {
+        self.buf[offset..offset + size_of::<u32>()].copy_from_slice(&value.to_le_bytes());
+    }
+
     pub fn write_value<T: SerToBuf>(&mut self, value: &T) -> OpenRgbResult<()> {
         value.serialize(self)
     }
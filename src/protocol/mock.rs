@@ -0,0 +1,527 @@
+//! In-process mock of the OpenRGB SDK server, used by unit tests.
+//!
+//! The mock listens on a random local port and answers requests from the `ControllerData`
+//! it was started with. Every packet it receives is recorded, so tests can assert on what the
+//! client actually sent over the wire.
+
+// not every test uses every helper
+#![allow(dead_code)]
+
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use flagset::FlagSet;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::data::{
+    ColorMode, ControllerData, DeviceType, Direction, Led, ModeData, ModeFlag, ProtocolOption,
+    ZoneData, ZoneType,
+};
+use crate::protocol::OpenRgbPacket;
+use crate::{
+    Color, DEFAULT_PROTOCOL, OpenRgbClient, PacketId, PluginData, ReceivedMessage, WriteMessage,
+};
+
+/// A packet received by the mock server.
+#[derive(Debug, Clone)]
+pub(crate) struct MockPacket {
+    pub device_id: u32,
+    pub packet_id: PacketId,
+    pub data: Vec<u8>,
+}
+
+impl MockPacket {
+    /// Returns the payload of this packet as a `ReceivedMessage`.
+    pub fn message(&self, protocol_version: u32) -> ReceivedMessage<'_> {
+        ReceivedMessage::new(&self.data, protocol_version)
+    }
+}
+
+struct MockState {
+    protocol_version: u32,
+    controllers: Vec<ControllerData>,
+    /// Ids of controllers whose data is sent truncated.
+    malformed_controllers: Vec<usize>,
+    packets: Vec<MockPacket>,
+    profiles: Vec<String>,
+    plugins: Vec<PluginData>,
+    open_connections: usize,
+    total_connections: usize,
+    /// Number of upcoming connections to close right after accepting them.
+    refused_connections: usize,
+}
+
+/// Signal sent from the `MockServer` to all open connections.
+#[derive(Debug, Clone, Copy)]
+enum MockSignal {
+    /// Send a packet with the given id and an empty body, like a server-initiated notification.
+    Notify(PacketId),
+    /// Close the connection.
+    Disconnect,
+}
+
+/// Mock OpenRGB SDK server.
+///
+/// The server is stopped when this struct is dropped.
+pub(crate) struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    signals: tokio::sync::broadcast::Sender<MockSignal>,
+    task: JoinHandle<()>,
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl MockServer {
+    /// Starts a mock server that advertises `protocol_version` and serves `controllers`.
+    pub async fn start(protocol_version: u32, controllers: Vec<ControllerData>) -> Self {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .await
+            .expect("failed to bind mock server");
+        let addr = listener.local_addr().expect("mock server has no address");
+        let state = Arc::new(Mutex::new(MockState {
+            protocol_version,
+            controllers,
+            malformed_controllers: Vec::new(),
+            packets: Vec::new(),
+            profiles: Vec::new(),
+            plugins: Vec::new(),
+            open_connections: 0,
+            total_connections: 0,
+            refused_connections: 0,
+        }));
+        let (signals, _) = tokio::sync::broadcast::channel(16);
+
+        let task_state = Arc::clone(&state);
+        let task_signals = signals.clone();
+        let task = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let state = Arc::clone(&task_state);
+                {
+                    let mut state = state.lock().unwrap();
+                    if state.refused_connections > 0 {
+                        state.refused_connections -= 1;
+                        continue;
+                    }
+                    state.open_connections += 1;
+                    state.total_connections += 1;
+                }
+                let signals = task_signals.subscribe();
+                tokio::spawn(async move {
+                    handle_connection(stream, Arc::clone(&state), signals).await;
+                    state.lock().unwrap().open_connections -= 1;
+                });
+            }
+        });
+
+        Self {
+            addr,
+            state,
+            signals,
+            task,
+        }
+    }
+
+    /// Sends a packet with `packet_id` and an empty body to all open connections, e.g. `DeviceListUpdated`.
+    pub fn notify(&self, packet_id: PacketId) {
+        let _ = self.signals.send(MockSignal::Notify(packet_id));
+    }
+
+    /// Closes all open connections. The server keeps accepting new connections.
+    pub fn disconnect_all(&self) {
+        let _ = self.signals.send(MockSignal::Disconnect);
+    }
+
+    /// Closes the next `n` connections right after accepting them, before the handshake.
+    pub fn refuse_connections(&self, n: usize) {
+        self.state.lock().unwrap().refused_connections = n;
+    }
+
+    /// Returns the address this server listens on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Connects a new client to this server.
+    pub async fn client(&self) -> OpenRgbClient {
+        OpenRgbClient::connect_to(self.addr, DEFAULT_PROTOCOL)
+            .await
+            .expect("failed to connect to mock server")
+    }
+
+    /// Returns the names of the profiles stored on this server.
+    ///
+    /// Like some OpenRGB versions, the mock does not deduplicate saved profiles.
+    pub fn profiles(&self) -> Vec<String> {
+        self.state.lock().unwrap().profiles.clone()
+    }
+
+    /// Replaces the controllers of this server, like a rescan that found different devices.
+    pub fn set_controllers(&self, controllers: Vec<ControllerData>) {
+        self.state.lock().unwrap().controllers = controllers;
+    }
+
+    /// Sends the data of controller `id` truncated to half its length, so that it fails to parse.
+    pub fn set_malformed_controller(&self, id: usize) {
+        self.state.lock().unwrap().malformed_controllers.push(id);
+    }
+
+    /// Replaces the plugins installed on this server.
+    pub fn set_plugins(&self, plugins: Vec<PluginData>) {
+        self.state.lock().unwrap().plugins = plugins;
+    }
+
+    /// Replaces the profiles stored on this server.
+    pub fn set_profiles(&self, profiles: &[&str]) {
+        self.state.lock().unwrap().profiles = profiles.iter().map(|p| p.to_string()).collect();
+    }
+
+    /// Returns the number of currently open connections.
+    pub fn open_connections(&self) -> usize {
+        self.state.lock().unwrap().open_connections
+    }
+
+    /// Returns the number of connections accepted since the server started.
+    pub fn total_connections(&self) -> usize {
+        self.state.lock().unwrap().total_connections
+    }
+
+    /// Waits until exactly `n` connections are open.
+    pub async fn wait_for_open_connections(&self, n: usize) {
+        for _ in 0..200 {
+            if self.open_connections() == n {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!(
+            "mock server expected {n} open connections, got {}",
+            self.open_connections()
+        );
+    }
+
+    /// Returns all packets received so far, including the protocol handshake.
+    pub fn all_packets(&self) -> Vec<MockPacket> {
+        self.state.lock().unwrap().packets.clone()
+    }
+
+    /// Returns all packets received so far, excluding the protocol handshake.
+    pub fn packets(&self) -> Vec<MockPacket> {
+        self.all_packets()
+            .into_iter()
+            .filter(|p| p.packet_id != PacketId::RequestProtocolVersion)
+            .collect()
+    }
+
+    /// Returns received packets with the given `packet_id`.
+    pub fn packets_of(&self, packet_id: PacketId) -> Vec<MockPacket> {
+        self.packets()
+            .into_iter()
+            .filter(|p| p.packet_id == packet_id)
+            .collect()
+    }
+
+    /// Waits until at least `n` packets (excluding the handshake) have been received.
+    ///
+    /// Write-only packets are not acknowledged by the server, so tests use this to wait
+    /// until the mock has processed them.
+    pub async fn wait_for_packets(&self, n: usize) -> Vec<MockPacket> {
+        for _ in 0..200 {
+            let packets = self.packets();
+            if packets.len() >= n {
+                return packets;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("mock server expected {n} packets, got {:?}", self.packets());
+    }
+
+    /// Waits until at least `n` packets with the given `packet_id` have been received.
+    pub async fn wait_for_packets_of(&self, packet_id: PacketId, n: usize) -> Vec<MockPacket> {
+        for _ in 0..200 {
+            let packets = self.packets_of(packet_id);
+            if packets.len() >= n {
+                return packets;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!(
+            "mock server expected {n} {packet_id:?} packets, got {:?}",
+            self.packets()
+        );
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    state: Arc<Mutex<MockState>>,
+    mut signals: tokio::sync::broadcast::Receiver<MockSignal>,
+) {
+    let mut protocol_version = state.lock().unwrap().protocol_version;
+    loop {
+        let mut header = [0u8; 16];
+        tokio::select! {
+            read = stream.read_exact(&mut header) => {
+                if read.is_err() {
+                    return;
+                }
+            }
+            signal = signals.recv() => {
+                match signal {
+                    Ok(MockSignal::Notify(packet_id)) => {
+                        if write_packet(&mut stream, 0, packet_id, &[]).await.is_err() {
+                            return;
+                        }
+                    }
+                    Ok(MockSignal::Disconnect) | Err(_) => return,
+                }
+                continue;
+            }
+        }
+        let mut msg = ReceivedMessage::new(&header, 0);
+        let magic = msg.read_value::<[u8; 4]>().unwrap();
+        assert_eq!(&magic, b"ORGB", "mock server received invalid magic");
+        let device_id = msg.read_u32().unwrap();
+        let packet_id = msg.read_value::<PacketId>().unwrap();
+        let size = msg.read_u32().unwrap() as usize;
+        let mut data = vec![0u8; size];
+        if stream.read_exact(&mut data).await.is_err() {
+            return;
+        }
+
+        let packet = MockPacket {
+            device_id,
+            packet_id,
+            data,
+        };
+        let response = {
+            let mut state = state.lock().unwrap();
+            let response = respond(&mut state, &packet, &mut protocol_version);
+            state.packets.push(packet);
+            response
+        };
+
+        if let Some(response) = response {
+            if write_packet(&mut stream, device_id, packet_id, &response)
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+/// Writes a packet with the given header fields and `body` to `stream`.
+async fn write_packet(
+    stream: &mut TcpStream,
+    device_id: u32,
+    packet_id: PacketId,
+    body: &[u8],
+) -> std::io::Result<()> {
+    let mut buf = WriteMessage::new(0);
+    buf.write_slice(b"ORGB");
+    buf.write_u32(device_id);
+    buf.write_value(&packet_id).unwrap();
+    buf.write_u32(body.len() as u32);
+    buf.write_slice(body);
+    stream.write_all(buf.bytes()).await
+}
+
+/// Returns the response payload for `packet`, or `None` if the packet has no response.
+fn respond(
+    state: &mut MockState,
+    packet: &MockPacket,
+    protocol_version: &mut u32,
+) -> Option<Vec<u8>> {
+    let mut buf = WriteMessage::new(*protocol_version);
+    match packet.packet_id {
+        PacketId::RequestProtocolVersion => {
+            let client_version = packet.message(0).read_u32().unwrap_or(0);
+            *protocol_version = client_version.min(state.protocol_version);
+            buf.write_u32(state.protocol_version);
+        }
+        PacketId::RequestControllerCount => {
+            buf.write_u32(state.controllers.len() as u32);
+        }
+        PacketId::RequestControllerData => {
+            let controller = state.controllers.get(packet.device_id as usize)?;
+            let response = OpenRgbPacket::new(packet.packet_id, controller);
+            buf.write_value(&response).unwrap();
+            if state
+                .malformed_controllers
+                .contains(&(packet.device_id as usize))
+            {
+                return Some(buf.bytes()[..buf.len() / 2].to_vec());
+            }
+        }
+        PacketId::RequestProfileList => {
+            let packet = OpenRgbPacket::new(packet.packet_id, &state.profiles);
+            buf.write_value(&packet).unwrap();
+        }
+        PacketId::RequestPluginList => {
+            let packet = OpenRgbPacket::new(packet.packet_id, &state.plugins);
+            buf.write_value(&packet).unwrap();
+        }
+        PacketId::RequestSaveProfile => {
+            state.profiles.push(raw_string(&packet.data));
+            return None;
+        }
+        PacketId::RequestDeleteProfile => {
+            let name = raw_string(&packet.data);
+            state.profiles.retain(|p| *p != name);
+            return None;
+        }
+        PacketId::RGBControllerUpdateMode => {
+            let mut msg = packet.message(*protocol_version);
+            let _data_size = msg.read_u32().ok()?;
+            let index = msg.read_u32().ok()?;
+            let mut mode = msg.read_value::<ModeData>().ok()?;
+            let controller = state.controllers.get_mut(packet.device_id as usize)?;
+            mode.index = index;
+            *controller.modes.get_mut(index as usize)? = mode;
+            controller.active_mode = index as i32;
+            return None;
+        }
+        _ => return None,
+    }
+    Some(buf.bytes().to_vec())
+}
+
+/// Decodes a null-terminated string without length prefix.
+fn raw_string(data: &[u8]) -> String {
+    let data = data.strip_suffix(b"\0").unwrap_or(data);
+    String::from_utf8_lossy(data).into_owned()
+}
+
+/// Returns a mode with the given `name` and `flags`.
+pub(crate) fn mode_fixture(name: &str, flags: impl Into<FlagSet<ModeFlag>>) -> ModeData {
+    ModeData {
+        name: name.to_string(),
+        value: 0,
+        flags: flags.into(),
+        speed_min: 0,
+        speed_max: 0,
+        speed: 0,
+        brightness_min: ProtocolOption::Some(0),
+        brightness_max: ProtocolOption::Some(0),
+        brightness: ProtocolOption::Some(0),
+        color_mode: ColorMode::None,
+        colors: Vec::new(),
+        colors_min: 0,
+        colors_max: 0,
+        direction: Direction::Left,
+        index: u32::MAX,
+    }
+}
+
+/// Returns a linear zone with `leds_count` LEDs.
+pub(crate) fn zone_fixture(name: &str, leds_count: u32) -> ZoneData {
+    ZoneData {
+        id: u32::MAX,
+        name: name.to_string(),
+        zone_type: ZoneType::Linear,
+        leds_min: leds_count,
+        leds_max: leds_count,
+        leds_count,
+        segments: ProtocolOption::Some(Vec::new()),
+        flags: ProtocolOption::Some(FlagSet::default()),
+        matrix: None,
+    }
+}
+
+/// Returns a controller named `name` with one linear zone per entry in `zone_sizes`.
+///
+/// The controller has a "Direct" (per-LED) mode, which is active, and a "Static" mode.
+pub(crate) fn controller_fixture(name: &str, zone_sizes: &[u32]) -> ControllerData {
+    let zones = zone_sizes
+        .iter()
+        .enumerate()
+        .map(|(i, size)| {
+            let mut zone = zone_fixture(&format!("Zone {i}"), *size);
+            zone.id = i as u32;
+            zone
+        })
+        .collect::<Vec<_>>();
+    let num_leds = zone_sizes.iter().sum::<u32>() as usize;
+    let mut direct = mode_fixture("Direct", ModeFlag::HasPerLEDColor);
+    direct.color_mode = ColorMode::PerLED;
+    let mut static_mode = mode_fixture("Static", ModeFlag::HasModeSpecificColor);
+    static_mode.color_mode = ColorMode::ModeSpecific;
+    static_mode.colors = vec![Color::new(255, 0, 0)];
+    static_mode.colors_min = 1;
+    static_mode.colors_max = 1;
+    let mut modes = vec![direct, static_mode];
+    for (i, mode) in modes.iter_mut().enumerate() {
+        mode.index = i as u32;
+    }
+
+    ControllerData {
+        device_type: DeviceType::LEDStrip,
+        name: name.to_string(),
+        vendor: "Vendor".to_string(),
+        description: format!("{name} Device"),
+        version: "1.0".to_string(),
+        serial: "SERIAL".to_string(),
+        location: "Location".to_string(),
+        active_mode: 0,
+        modes,
+        zones,
+        leds: (0..num_leds)
+            .map(|i| Led {
+                name: format!("LED {i}"),
+                value: i as u32,
+            })
+            .collect(),
+        colors: vec![Color::default(); num_leds],
+        led_alt_names: ProtocolOption::Some(Vec::new()),
+        flags: ProtocolOption::Some(FlagSet::default()),
+        id: u32::MAX,
+        protocol_version: DEFAULT_PROTOCOL,
+    }
+}
+
+/// Returns a protocol version 3 `RequestControllerData` response of a Thermaltake Riing, as captured from an OpenRGB server.
+pub(crate) fn thermaltake_riing() -> WriteMessage {
+    let mut buf = WriteMessage::new(3);
+    buf.write_u32(760);
+    buf.write_slice(&[
+        3, 0, 0, 0, 18, 0, 84, 104, 101, 114, 109, 97, 108, 116, 97, 107, 101, 32, 82, 105, 105,
+        110, 103, 0, 12, 0, 84, 104, 101, 114, 109, 97, 108, 116, 97, 107, 101, 0, 25, 0, 84, 104,
+        101, 114, 109, 97, 108, 116, 97, 107, 101, 32, 82, 105, 105, 110, 103, 32, 68, 101, 118,
+        105, 99, 101, 0, 1, 0, 0, 1, 0, 0, 19, 0, 72, 73, 68, 58, 32, 47, 100, 101, 118, 47, 104,
+        105, 100, 114, 97, 119, 49, 48, 0, 8, 0, 0, 0, 0, 0, 7, 0, 68, 105, 114, 101, 99, 116, 0,
+        24, 0, 0, 0, 32, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 7, 0, 83, 116, 97, 116, 105,
+        99, 0, 25, 0, 0, 0, 64, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0,
+        0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 0, 0, 5, 0, 70,
+        108, 111, 119, 0, 0, 0, 0, 0, 1, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 9, 0, 83,
+        112, 101, 99, 116, 114, 117, 109, 0, 4, 0, 0, 0, 1, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 7, 0, 82, 105, 112, 112, 108, 101, 0, 8, 0, 0, 0, 33, 0, 0, 0, 3, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+        0, 0, 0, 0, 0, 6, 0, 66, 108, 105, 110, 107, 0, 12, 0, 0, 0, 33, 0, 0, 0, 3, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        1, 0, 0, 0, 0, 0, 6, 0, 80, 117, 108, 115, 101, 0, 16, 0, 0, 0, 33, 0, 0, 0, 3, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 1, 0, 0, 0, 0, 0, 5, 0, 87, 97, 118, 101, 0, 20, 0, 0, 0, 33, 0, 0, 0, 3, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        1, 0, 0, 0, 0, 0, 5, 0, 16, 0, 82, 105, 105, 110, 103, 32, 67, 104, 97, 110, 110, 101, 108,
+        32, 49, 0, 1, 0, 0, 0, 0, 0, 0, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 16, 0, 82, 105, 105, 110,
+        103, 32, 67, 104, 97, 110, 110, 101, 108, 32, 50, 0, 1, 0, 0, 0, 0, 0, 0, 0, 20, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 16, 0, 82, 105, 105, 110, 103, 32, 67, 104, 97, 110, 110, 101, 108, 32,
+        51, 0, 1, 0, 0, 0, 0, 0, 0, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 16, 0, 82, 105, 105, 110,
+        103, 32, 67, 104, 97, 110, 110, 101, 108, 32, 52, 0, 1, 0, 0, 0, 0, 0, 0, 0, 20, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 16, 0, 82, 105, 105, 110, 103, 32, 67, 104, 97, 110, 110, 101, 108, 32,
+        53, 0, 1, 0, 0, 0, 0, 0, 0, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    ]);
+    buf
+}
@@ -5,7 +5,7 @@ use std::sync::Arc;
 use tokio::net::ToSocketAddrs;
 use tokio::sync::Mutex;
 
-use super::data::{Color, ControllerData, ModeData, RawString, SegmentData};
+use super::data::{Color, ControllerData, ModeData, ProtocolOption, RawString, SegmentData};
 use crate::{OpenRgbError, OpenRgbResult, PluginData};
 
 /// Default protocol version used by [OpenRGB] client.
@@ -14,11 +14,18 @@ pub const DEFAULT_PROTOCOL: u32 = 5;
 /// Default address used by [OpenRGB::connect].
 pub const DEFAULT_ADDR: (Ipv4Addr, u16) = (Ipv4Addr::LOCALHOST, 6742);
 
+/// Highest protocol version a server is expected to report during the handshake.
+///
+/// Anything higher most likely means the server is not an OpenRGB server, or it sent the version big-endian.
+const MAX_PLAUSIBLE_PROTOCOL: u32 = 100;
+
 /// Device ID to use when no specific device is targeted.
 const NO_DEVICE_ID: u32 = 0;
 
 pub mod data;
 mod deserialize;
+#[cfg(test)]
+pub(crate) mod mock;
 mod packet;
 mod serialize;
 mod stream;
@@ -33,6 +40,8 @@ pub(crate) use {deserialize::*, packet::*, serialize::*, stream::*};
 #[derive(Clone)]
 pub(crate) struct OpenRgbProtocol {
     protocol_id: u32,
+    /// Protocol version the server reported, before capping it to [DEFAULT_PROTOCOL].
+    server_protocol: u32,
     stream: Arc<Mutex<ProtocolStream>>,
 }
 
@@ -46,12 +55,12 @@ impl OpenRgbProtocol {
     ///
     /// # Example
     /// ```no_run
-    /// # use openrgb::OpenRGB;
+    /// # use openrgb::OpenRgbClient;
     /// # use std::error::Error;
     /// #
     /// # #[tokio::main]
     /// # async fn main() -> Result<(), Box<dyn Error>> {
-    /// let client = OpenRGB::connect_to(("localhost", 6742)).await?;
+    /// let client = OpenRgbClient::connect_to(("localhost", 6742), 5).await?;
     /// #
     /// # Ok(())
     /// # }
@@ -69,6 +78,14 @@ impl OpenRgbProtocol {
             })?;
         Self::new(stream).await
     }
+
+    /// Opens a new, separate connection to the same server as this one.
+    ///
+    /// Requests on the returned protocol do not wait on requests on this one, and vice versa.
+    pub async fn connect_clone(&self) -> OpenRgbResult<Self> {
+        let addr = self.peer_addr().await?;
+        Self::connect_to(addr, self.protocol_id).await
+    }
 }
 
 impl OpenRgbProtocol {
@@ -76,13 +93,27 @@ impl OpenRgbProtocol {
     ///
     /// This constructor expects a connected, ready to use stream.
     pub async fn new(mut stream: ProtocolStream) -> OpenRgbResult<Self> {
-        let req_protocol = stream
+        let req_protocol: u32 = stream
             .request(
                 NO_DEVICE_ID,
                 PacketId::RequestProtocolVersion,
                 &DEFAULT_PROTOCOL,
             )
             .await?;
+        if req_protocol > MAX_PLAUSIBLE_PROTOCOL {
+            let bytes = req_protocol.to_le_bytes();
+            if bytes.iter().all(|b| b.is_ascii_graphic() || *b == b' ') {
+                return Err(OpenRgbError::ProtocolError(format!(
+                    "Server reported protocol version {req_protocol:#010x}, which reads as the text {:?}: \
+                    the address most likely points at a service that is not an OpenRGB SDK server",
+                    String::from_utf8_lossy(&bytes)
+                )));
+            }
+            return Err(OpenRgbError::ProtocolError(format!(
+                "Server reported implausible protocol version {req_protocol} ({req_protocol:#010x}), \
+                protocol endianness/format mismatch: is this an OpenRGB server?"
+            )));
+        }
         let protocol = DEFAULT_PROTOCOL.min(req_protocol);
 
         tracing::debug!(
@@ -93,10 +124,50 @@ impl OpenRgbProtocol {
 
         Ok(Self {
             protocol_id: protocol,
+            server_protocol: req_protocol,
             stream: Arc::new(Mutex::new(stream)),
         })
     }
 
+    /// Closes the connection of this protocol.
+    ///
+    /// All clones of this protocol share the connection, requests on them will fail afterwards.
+    pub async fn close(&self) -> OpenRgbResult<()> {
+        self.stream.lock().await.shutdown().await?;
+        Ok(())
+    }
+
+    /// Returns true if `self` and `other` use the same connection.
+    #[cfg(test)]
+    pub fn same_connection(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.stream, &other.stream)
+    }
+
+    /// Build a new client from a stream on which the protocol version was already negotiated.
+    ///
+    /// Unlike [OpenRgbProtocol::new], this does not send a `RequestProtocolVersion` request,
+    /// `protocol_version` is assumed to be the version agreed upon with the server.
+    pub fn from_connected_stream(mut stream: ProtocolStream, protocol_version: u32) -> Self {
+        stream.set_protocol_version(protocol_version);
+        Self {
+            protocol_id: protocol_version,
+            server_protocol: protocol_version,
+            stream: Arc::new(Mutex::new(stream)),
+        }
+    }
+
+    /// Waits for the next packet the server sends on its own, like `DeviceListUpdated`, and returns its raw packet id.
+    ///
+    /// This holds the connection until a packet arrives, so it should only be used on a dedicated connection.
+    pub async fn read_notification(&self) -> OpenRgbResult<u32> {
+        self.stream.lock().await.read_notification().await
+    }
+
+    /// Returns the address of the server this protocol is connected to.
+    pub async fn peer_addr(&self) -> OpenRgbResult<std::net::SocketAddr> {
+        Ok(self.stream.lock().await.peer_addr()?)
+    }
+
     /// Get protocol version negotiated with server.
     ///
     /// This is the lowest between this client maximum supported version ([DEFAULT_PROTOCOL]) and server version.
@@ -106,17 +177,28 @@ impl OpenRgbProtocol {
         self.protocol_id
     }
 
+    /// Get protocol version the server reported during the handshake.
+    ///
+    /// This can be higher than the negotiated version. For protocols built with [OpenRgbProtocol::from_connected_stream],
+    /// the server version is unknown and this returns the given version.
+    pub fn get_server_protocol_version(&self) -> u32 {
+        self.server_protocol
+    }
+
     /// Helper method to write a packet to the server.
+    ///
+    /// The data size is prepended if packets with `packet_id` have one, see [DataSizePrefix].
     async fn write_packet<T: SerToBuf>(
         &self,
         device_id: u32,
         packet_id: PacketId,
-        data: &T,
+        contents: T,
     ) -> OpenRgbResult<()> {
+        let packet = OpenRgbPacket::new(packet_id, contents);
         self.stream
             .lock()
             .await
-            .write_packet(device_id, packet_id, data)
+            .write_packet(device_id, packet_id, &packet)
             .await
     }
 
@@ -158,11 +240,13 @@ impl OpenRgbProtocol {
     ///
     /// See [Open SDK documentation](https://gitlab.com/CalcProgrammer1/OpenRGB/-/wikis/OpenRGB-SDK-Documentation#net_packet_id_request_controller_data) for more information.
     pub async fn get_controller(&self, controller_id: u32) -> OpenRgbResult<ControllerData> {
+        // the protocol version argument was added in protocol version 1
+        let protocol_arg = ProtocolOption::<1, u32>::Some(self.protocol_id);
         let mut c: ControllerData = self
             .request(
                 controller_id,
                 PacketId::RequestControllerData,
-                &self.protocol_id,
+                &protocol_arg,
             )
             .await?;
         c.id = controller_id;
@@ -198,7 +282,7 @@ impl OpenRgbProtocol {
         self.write_packet(
             controller_id,
             PacketId::RGBControllerUpdateSingleLed,
-            &(led_id, color),
+            (led_id, color),
         )
         .await
     }
@@ -212,8 +296,7 @@ impl OpenRgbProtocol {
     ///
     /// See [Open SDK documentation](https://gitlab.com/CalcProgrammer1/OpenRGB/-/wikis/OpenRGB-SDK-Documentation#net_packet_id_rgbcontroller_updateleds) for more information.
     pub async fn update_leds(&self, controller_id: u32, colors: &[Color]) -> OpenRgbResult<()> {
-        let packet = OpenRgbPacket::new(colors);
-        self.write_packet(controller_id, PacketId::RGBControllerUpdateLeds, &packet)
+        self.write_packet(controller_id, PacketId::RGBControllerUpdateLeds, colors)
             .await
     }
 
@@ -226,11 +309,10 @@ impl OpenRgbProtocol {
         zone_id: u32,
         colors: &[Color],
     ) -> OpenRgbResult<()> {
-        let packet = OpenRgbPacket::new((zone_id, colors));
         self.write_packet(
             controller_id,
             PacketId::RGBControllerUpdateZoneLeds,
-            &packet,
+            (zone_id, colors),
         )
         .await
     }
@@ -238,10 +320,24 @@ impl OpenRgbProtocol {
     /// Update a mode. This sets it to the current mode.
     ///
     /// See [Open SDK documentation](https://gitlab.com/CalcProgrammer1/OpenRGB/-/wikis/OpenRGB-SDK-Documentation#net_packet_id_rgbcontroller_updatemode) for more information.
+    ///
+    /// Returns an error without sending anything if the mode has colors, but not between `colors_min` and `colors_max` of them.
     pub async fn update_mode(&self, controller_id: u32, mode: &ModeData) -> OpenRgbResult<()> {
-        let packet = OpenRgbPacket::new((mode.index, mode));
-        self.write_packet(controller_id, PacketId::RGBControllerUpdateMode, &packet)
-            .await
+        if let (Some(min), Some(max)) = (mode.colors_min(), mode.colors_max()) {
+            let len = mode.colors.len();
+            if !(min as usize..=max as usize).contains(&len) {
+                return Err(OpenRgbError::CommandError(format!(
+                    "Mode {} has {len} colors, but requires between {min} and {max} colors",
+                    mode.name
+                )));
+            }
+        }
+        self.write_packet(
+            controller_id,
+            PacketId::RGBControllerUpdateMode,
+            (mode.index, mode),
+        )
+        .await
     }
 
     /// Set custom mode.
@@ -262,9 +358,7 @@ impl OpenRgbProtocol {
     /// See [Open SDK documentation](https://gitlab.com/CalcProgrammer1/OpenRGB/-/wikis/OpenRGB-SDK-Documentation#net_packet_id_request_profile_list) for more information.
     pub async fn get_profiles(&self) -> OpenRgbResult<Vec<String>> {
         self.check_protocol_version(2, "Get profiles")?;
-        self.request::<_, (u32, Vec<String>)>(0, PacketId::RequestProfileList, &())
-            .await
-            .map(|(_size, profiles)| profiles)
+        self.request(0, PacketId::RequestProfileList, &()).await
     }
 
     /// Load a profile.
@@ -281,7 +375,7 @@ impl OpenRgbProtocol {
     /// See [Open SDK documentation](https://gitlab.com/CalcProgrammer1/OpenRGB/-/wikis/OpenRGB-SDK-Documentation#net_packet_id_request_save_profile) for more information.
     pub async fn save_profile(&self, name: impl Into<String>) -> OpenRgbResult<()> {
         self.check_protocol_version(2, "Save profiles")?;
-        self.write_packet(0, PacketId::RequestSaveProfile, &name.into())
+        self.write_packet(0, PacketId::RequestSaveProfile, &RawString(&name.into()))
             .await
     }
 
@@ -290,7 +384,7 @@ impl OpenRgbProtocol {
     /// See [Open SDK documentation](https://gitlab.com/CalcProgrammer1/OpenRGB/-/wikis/OpenRGB-SDK-Documentation#net_packet_id_request_delete_profile) for more information.
     pub async fn delete_profile(&self, name: impl Into<String>) -> OpenRgbResult<()> {
         self.check_protocol_version(2, "Delete profiles")?;
-        self.write_packet(0, PacketId::RequestDeleteProfile, &name.into())
+        self.write_packet(0, PacketId::RequestDeleteProfile, &RawString(&name.into()))
             .await
     }
 
@@ -299,19 +393,19 @@ impl OpenRgbProtocol {
     /// See [Open SDK documentation](https://gitlab.com/CalcProgrammer1/OpenRGB/-/wikis/OpenRGB-SDK-Documentation#net_packet_id_rgbcontroller_savemode) for more information.
     pub async fn save_mode(&self, controller_id: u32, mode: &ModeData) -> OpenRgbResult<()> {
         self.check_protocol_version(3, "Save mode")?;
-        let packet = OpenRgbPacket::new((mode.index, mode));
-        self.write_packet(controller_id, PacketId::RGBControllerSaveMode, &packet)
-            .await
+        self.write_packet(
+            controller_id,
+            PacketId::RGBControllerSaveMode,
+            (mode.index, mode),
+        )
+        .await
     }
 
     /// Returns a a list of names of installed plugins.
     pub async fn get_plugins(&self) -> OpenRgbResult<Vec<PluginData>> {
         self.check_protocol_version(4, "Request Plugin List")?;
-        // response contains length of data in the packet
-        let resp: (u32, Vec<_>) = self
-            .request(NO_DEVICE_ID, PacketId::RequestPluginList, &())
-            .await?;
-        Ok(resp.1)
+        self.request(NO_DEVICE_ID, PacketId::RequestPluginList, &())
+            .await
     }
 
     /// Performs a plugin specific command. Depends on the plugin what this does.
@@ -336,9 +430,12 @@ impl OpenRgbProtocol {
     ) -> OpenRgbResult<()> {
         // segments are version 4, segments commands are version 5
         self.check_protocol_version(5, "Add Segment")?;
-        let packet = OpenRgbPacket::new((zone_id, segment));
-        self.write_packet(controller_id, PacketId::RGBControllerAddSegment, &packet)
-            .await
+        self.write_packet(
+            controller_id,
+            PacketId::RGBControllerAddSegment,
+            (zone_id, segment),
+        )
+        .await
     }
 
     pub async fn clear_segments(&self, controller_id: u32) -> OpenRgbResult<()> {
@@ -354,7 +451,7 @@ impl OpenRgbProtocol {
             .await
     }
 
-    fn check_protocol_version(&self, min: u32, msg: &str) -> OpenRgbResult<()> {
+    pub(crate) fn check_protocol_version(&self, min: u32, msg: &str) -> OpenRgbResult<()> {
         if self.protocol_id < min {
             return Err(OpenRgbError::UnsupportedOperation {
                 operation: msg.to_owned(),
@@ -379,8 +476,88 @@ mod tests {
         DEFAULT_PROTOCOL,
         OpenRgbProtocol,
         OpenRgbResult,
+        PacketId,
+        protocol::mock::{MockServer, controller_fixture},
     };
 
+    #[tokio::test]
+    async fn test_update_mode_color_count() -> OpenRgbResult<()> {
+        let mock = MockServer::start(5, vec![controller_fixture("Strip", &[1])]).await;
+        let client = OpenRgbProtocol::connect_to(mock.addr(), DEFAULT_PROTOCOL).await?;
+        let controller = client.get_controller(0).await?;
+
+        // "Static" requires exactly 1 color
+        let mut mode = controller.modes[1].clone();
+        mode.colors = vec![Color::new(255, 0, 0); 2];
+        let err = client.update_mode(0, &mode).await.unwrap_err();
+        assert!(err.to_string().contains("between 1 and 1"), "{err}");
+
+        mode.colors.truncate(1);
+        client.update_mode(0, &mode).await?;
+        let packets = mock
+            .wait_for_packets_of(PacketId::RGBControllerUpdateMode, 1)
+            .await;
+        assert_eq!(packets.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reject_byte_swapped_version() {
+        let mock = MockServer::start(5u32.swap_bytes(), vec![]).await;
+        let res = OpenRgbProtocol::connect_to(mock.addr(), DEFAULT_PROTOCOL).await;
+        match res {
+            Err(crate::OpenRgbError::ProtocolError(msg)) => assert!(msg.contains("endianness")),
+            Err(e) => panic!("unexpected error: {e}"),
+            Ok(_) => panic!("connecting should fail"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reject_text_version() {
+        // "HTTP" read as little-endian u32
+        let mock = MockServer::start(0x50545448, vec![]).await;
+        let res = OpenRgbProtocol::connect_to(mock.addr(), DEFAULT_PROTOCOL).await;
+        match res {
+            Err(crate::OpenRgbError::ProtocolError(msg)) => {
+                assert!(msg.contains("\"HTTP\""), "{msg}");
+                assert!(msg.contains("not an OpenRGB SDK server"), "{msg}");
+            }
+            Err(e) => panic!("unexpected error: {e}"),
+            Ok(_) => panic!("connecting should fail"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_controller_protocol_argument() -> OpenRgbResult<()> {
+        for version in [0, 1, 4, 5] {
+            let mock = MockServer::start(version, vec![controller_fixture("Strip", &[2])]).await;
+            let client = OpenRgbProtocol::connect_to(mock.addr(), DEFAULT_PROTOCOL).await?;
+            let controller = client.get_controller(0).await?;
+            assert_eq!(controller.name, "Strip");
+            assert_eq!(controller.location, "Location");
+
+            let packets = mock.packets_of(PacketId::RequestControllerData);
+            let expected = match version {
+                0 => vec![],
+                _ => version.to_le_bytes().to_vec(),
+            };
+            assert_eq!(packets[0].data, expected, "protocol version {version}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_controller_vendor() -> OpenRgbResult<()> {
+        let mock = MockServer::start(0, vec![controller_fixture("Strip", &[2])]).await;
+        let client = OpenRgbProtocol::connect_to(mock.addr(), DEFAULT_PROTOCOL).await?;
+        assert_eq!(client.get_controller(0).await?.vendor, "");
+
+        let mock = MockServer::start(1, vec![controller_fixture("Strip", &[2])]).await;
+        let client = OpenRgbProtocol::connect_to(mock.addr(), DEFAULT_PROTOCOL).await?;
+        assert_eq!(client.get_controller(0).await?.vendor, "Vendor");
+        Ok(())
+    }
+
     // create test methods for each of the OpenRGBProtocol methods
 
     #[tokio::test]
@@ -5,6 +5,20 @@ pub(crate) trait DeserFromBuf {
     fn deserialize(buf: &mut ReceivedMessage<'_>) -> OpenRgbResult<Self>
     where
         Self: Sized;
+
+    /// Reads `n` consecutive values.
+    ///
+    /// Fixed-size types can override this to read the whole block at once.
+    fn deserialize_n(buf: &mut ReceivedMessage<'_>, n: usize) -> OpenRgbResult<Vec<Self>>
+    where
+        Self: Sized,
+    {
+        let mut values = Vec::with_capacity(n);
+        for _ in 0..n {
+            values.push(Self::deserialize(buf)?);
+        }
+        Ok(values)
+    }
 }
 
 pub(crate) struct ReceivedMessage<'a> {
@@ -38,6 +52,11 @@ impl<'a> ReceivedMessage<'a> {
         self.protocol_version
     }
 
+    /// Returns the number of bytes that have not been read yet.
+    pub fn remaining(&self) -> usize {
+        self.available_buf().len()
+    }
+
     fn available_buf(&self) -> &[u8] {
         &self.buf[self.idx..]
     }
@@ -79,6 +98,19 @@ impl<'a> ReceivedMessage<'a> {
         Ok(value)
     }
 
+    /// Reads the next `len` bytes as a slice.
+    pub fn read_slice(&mut self, len: usize) -> OpenRgbResult<&'a [u8]> {
+        if self.remaining() < len {
+            return Err(OpenRgbError::ProtocolError(format!(
+                "Not enough bytes to read {len} bytes, {} remaining",
+                self.remaining()
+            )));
+        }
+        let slice = &self.buf[self.idx..self.idx + len];
+        self.idx += len;
+        Ok(slice)
+    }
+
     pub fn read_value<T: DeserFromBuf>(&mut self) -> OpenRgbResult<T> {
         T::deserialize(self)
     }
@@ -87,11 +119,7 @@ impl<'a> ReceivedMessage<'a> {
     ///
     /// If there's a `[len, [..data]]` format, use `read_value::<Vec<T>>()` instead.
     pub fn read_n_values<T: DeserFromBuf>(&mut self, n: usize) -> OpenRgbResult<Vec<T>> {
-        let mut values = Vec::with_capacity(n);
-        for _ in 0..n {
-            values.push(T::deserialize(self)?);
-        }
-        Ok(values)
+        T::deserialize_n(self, n)
     }
 }
 
@@ -7,42 +7,103 @@ use tokio::{
     net::{TcpStream, ToSocketAddrs},
 };
 
-/// Utility struct to write packets.
-/// Some packets need to be prepended by their length.
-/// This struct serializes the contents and prepends the length to the buffer.
+/// Whether the payload of a packet starts with a `u32` data size.
+///
+/// The header already contains the payload size, but some payloads repeat it as their first field.
+/// Every payload that has this field counts the field itself, e.g. `RGBControllerUpdateLeds` with 2 colors
+/// sends a data size of 14: 4 bytes for the data size, 2 for the color count and 8 for the colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DataSizePrefix {
+    /// The payload has no data size field.
+    None,
+    /// The payload starts with a `u32` holding the payload length, including the data size field itself.
+    IncludingSelf,
+}
+
+impl DataSizePrefix {
+    /// Returns how the payload of packets with `packet_id` is prefixed.
+    ///
+    /// For requests that are answered, this describes the response.
+    pub(crate) fn of(packet_id: PacketId) -> Self {
+        match packet_id {
+            PacketId::RequestControllerData
+            | PacketId::RequestProfileList
+            | PacketId::RequestPluginList
+            | PacketId::RGBControllerAddSegment
+            | PacketId::RGBControllerUpdateLeds
+            | PacketId::RGBControllerUpdateZoneLeds
+            | PacketId::RGBControllerUpdateMode
+            | PacketId::RGBControllerSaveMode => Self::IncludingSelf,
+            _ => Self::None,
+        }
+    }
+
+    /// Reads the data size at the start of `buf`, if payloads with this prefix have one,
+    /// and checks that it matches the length of the payload.
+    pub(crate) fn read(self, buf: &mut ReceivedMessage<'_>) -> OpenRgbResult<()> {
+        if self == Self::None {
+            return Ok(());
+        }
+        let len = buf.remaining();
+        let data_size = buf.read_u32()? as usize;
+        if data_size != len {
+            return Err(OpenRgbError::ProtocolError(format!(
+                "Data size {data_size} does not match payload length {len}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Utility struct to write packet payloads.
+/// Some payloads need to be prepended by their length, see [DataSizePrefix].
+/// This struct serializes the contents and prepends the length to the buffer if the packet needs it.
 pub(crate) struct OpenRgbPacket<T: SerToBuf> {
+    pub prefix: DataSizePrefix,
     pub contents: T,
 }
 
 impl<T: SerToBuf> OpenRgbPacket<T> {
-    pub fn new(contents: T) -> OpenRgbPacket<T> {
-        Self { contents }
+    /// Creates the payload of a packet with `packet_id`.
+    pub fn new(packet_id: PacketId, contents: T) -> OpenRgbPacket<T> {
+        Self {
+            prefix: DataSizePrefix::of(packet_id),
+            contents,
+        }
     }
 }
 
 impl<T: SerToBuf> SerToBuf for OpenRgbPacket<T> {
     fn serialize(&self, buf: &mut WriteMessage) -> OpenRgbResult<()> {
-        let mut inner_buf = WriteMessage::new(buf.protocol_version());
-        self.contents.serialize(&mut inner_buf)?;
-        let len = inner_buf.len() + size_of::<u32>(); // + u32 to account for the length field itself
-        buf.write_u32(len as u32);
-        buf.write_slice(inner_buf.bytes());
-        Ok(())
+        match self.prefix {
+            DataSizePrefix::None => self.contents.serialize(buf),
+            DataSizePrefix::IncludingSelf => {
+                // write the contents directly after a placeholder length, to avoid allocating a separate buffer
+                let start = buf.len();
+                buf.write_u32(0);
+                self.contents.serialize(buf)?;
+                let len = buf.len() - start; // includes the length field itself
+                buf.set_u32_at(start, len as u32);
+                Ok(())
+            }
+        }
     }
 }
 
 pub(crate) struct OpenRgbMessageHeader {
-    packet_id: PacketId,
+    /// Raw packet ID, kept as `u32` so that packets with unknown IDs can still be skipped.
+    packet_id: u32,
     device_id: u32,
     packet_size: u32,
 }
 
 impl OpenRgbMessageHeader {
     pub(crate) const MAGIC: [u8; 4] = *b"ORGB";
+    /// Length of a header in bytes.
+    const LEN: usize = 16;
 
     async fn read(stream: &mut TcpStream) -> OpenRgbResult<Self> {
-        // header is always 16 bytes long
-        let mut buf = [0u8; 16];
+        let mut buf = [0u8; Self::LEN];
         stream.read_exact(&mut buf).await?;
         let mut recv = ReceivedMessage::new(&buf, 0); // header is constant across protocol versions
         tracing::trace!("Read header: {}", recv);
@@ -54,7 +115,7 @@ impl OpenRgbMessageHeader {
         }
 
         let device_id = recv.read_u32()?;
-        let packet_id = recv.read_value::<PacketId>()?;
+        let packet_id = recv.read_u32()?;
         let packet_size = recv.read_u32()?;
         Ok(Self {
             device_id,
@@ -63,14 +124,11 @@ impl OpenRgbMessageHeader {
         })
     }
 
-    async fn write(&self, stream: &mut TcpStream) -> OpenRgbResult<()> {
-        let mut buf = WriteMessage::with_capacity(0, 16);
+    fn write(&self, buf: &mut WriteMessage) {
         buf.write_slice(&Self::MAGIC);
         buf.write_u32(self.device_id);
-        buf.write_value(&self.packet_id)?;
+        buf.write_u32(self.packet_id);
         buf.write_u32(self.packet_size);
-        stream.write_all(buf.bytes()).await?;
-        Ok(())
     }
 }
 
@@ -79,6 +137,8 @@ impl OpenRgbMessageHeader {
 pub(crate) struct ProtocolStream {
     stream: TcpStream,
     protocol_version: u32,
+    /// Buffer for outgoing packets, reused so that writing packets does not allocate in steady state.
+    write_buf: WriteMessage,
 }
 
 impl ProtocolStream {
@@ -87,16 +147,32 @@ impl ProtocolStream {
         protocol_version: u32,
     ) -> std::io::Result<Self> {
         let stream = TcpStream::connect(addr).await?;
-        Ok(Self {
+        Ok(Self::from_stream(stream, protocol_version))
+    }
+
+    /// Wraps an already connected `stream`, using `protocol_version` for all packets.
+    pub fn from_stream(stream: TcpStream, protocol_version: u32) -> Self {
+        Self {
             stream,
             protocol_version,
-        })
+            write_buf: WriteMessage::new(protocol_version),
+        }
     }
 
     pub fn protocol_version(&self) -> u32 {
         self.protocol_version
     }
 
+    /// Shuts down the write half of the connection, signalling the server that this client is done.
+    pub async fn shutdown(&mut self) -> std::io::Result<()> {
+        self.stream.shutdown().await
+    }
+
+    /// Returns the address of the server this stream is connected to.
+    pub fn peer_addr(&self) -> std::io::Result<std::net::SocketAddr> {
+        self.stream.peer_addr()
+    }
+
     pub fn set_protocol_version(&mut self, version: u32) {
         self.protocol_version = version;
     }
@@ -122,6 +198,7 @@ impl ProtocolStream {
         self.stream.read_exact(&mut buf).await?;
         let mut recv = ReceivedMessage::new(&buf, self.protocol_version());
         tracing::trace!("Read packet: {}", recv);
+        DataSizePrefix::of(packet_id).read(&mut recv)?;
         T::deserialize(&mut recv)
     }
 
@@ -131,42 +208,78 @@ impl ProtocolStream {
         packet_id: PacketId,
         data: &T,
     ) -> OpenRgbResult<()> {
-        // let mut buf = Vec::with_capacity(8);
-        let mut buf = WriteMessage::new(self.protocol_version());
-        data.serialize(&mut buf)?;
-        let packet_size = buf.len() as u32;
+        self.write_buf.clear(self.protocol_version);
+        // the packet size is filled in after writing the data
         let header = OpenRgbMessageHeader {
-            packet_id,
+            packet_id: packet_id.into(),
             device_id,
-            packet_size,
+            packet_size: 0,
         };
-        header.write(&mut self.stream).await?;
+        header.write(&mut self.write_buf);
+        data.serialize(&mut self.write_buf)?;
+        let packet_size = self.write_buf.len() - OpenRgbMessageHeader::LEN;
+        self.write_buf.set_u32_at(
+            OpenRgbMessageHeader::LEN - size_of::<u32>(),
+            packet_size as u32,
+        );
 
-        tracing::debug!("Writing packet: {}", buf);
-        self.stream.write_all(buf.bytes()).await?;
+        tracing::debug!("Writing packet: {}", self.write_buf);
+        self.stream.write_all(self.write_buf.bytes()).await?;
         Ok(())
     }
 
+    /// Reads the next packet sent by the server on its own, like `DeviceListUpdated`, and returns its raw packet id.
+    ///
+    /// The body of the packet is skipped.
+    pub async fn read_notification(&mut self) -> OpenRgbResult<u32> {
+        let header = OpenRgbMessageHeader::read(&mut self.stream).await?;
+        self.recover(&header).await?;
+        Ok(header.packet_id)
+    }
+
     async fn read_header(
         &mut self,
         device_id: u32,
         packet_id: PacketId,
     ) -> OpenRgbResult<OpenRgbMessageHeader> {
         let header = OpenRgbMessageHeader::read(&mut self.stream).await?;
-        if header.packet_id != packet_id {
+        if header.packet_id != u32::from(packet_id) {
+            self.recover(&header).await?;
+            let received = PacketId::try_from(header.packet_id)
+                .map(|id| format!("{id:?}"))
+                .unwrap_or("unknown packet".to_string());
             return Err(OpenRgbError::ProtocolError(format!(
-                "Unexpected packet ID: expected {:?}, got {:?}",
-                packet_id, header.packet_id
+                "Unexpected packet ID: expected {:?} ({}), got {} ({}) with size {}",
+                packet_id,
+                u32::from(packet_id),
+                received,
+                header.packet_id,
+                header.packet_size
             )));
         }
         if header.device_id != device_id {
+            self.recover(&header).await?;
             return Err(OpenRgbError::ProtocolError(format!(
-                "Unexpected device ID: expected {}, got {}",
-                device_id, header.device_id
+                "Unexpected device ID: expected {}, got {} for packet {} with size {}",
+                device_id, header.device_id, header.packet_id, header.packet_size
             )));
         }
         Ok(header)
     }
+
+    /// Skips the body of the packet belonging to `header`.
+    ///
+    /// This leaves the stream at the start of the next packet, so it stays usable after an unexpected packet.
+    async fn recover(&mut self, header: &OpenRgbMessageHeader) -> OpenRgbResult<()> {
+        tracing::debug!(
+            "Skipping {} bytes of unexpected packet {}",
+            header.packet_size,
+            header.packet_id
+        );
+        let mut body = (&mut self.stream).take(header.packet_size as u64);
+        tokio::io::copy(&mut body, &mut tokio::io::sink()).await?;
+        Ok(())
+    }
 }
 
 impl AsyncRead for ProtocolStream {
@@ -206,3 +319,163 @@ impl AsyncWrite for ProtocolStream {
         AsyncWrite::poll_shutdown(pin, cx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    fn raw_packet(packet_id: PacketId, body: &[u8]) -> Vec<u8> {
+        let mut buf = WriteMessage::new(0);
+        buf.write_slice(&OpenRgbMessageHeader::MAGIC);
+        buf.write_u32(0);
+        buf.write_u32(packet_id.into());
+        buf.write_u32(body.len() as u32);
+        buf.write_slice(body);
+        buf.bytes().to_vec()
+    }
+
+    #[tokio::test]
+    async fn test_recover_after_unexpected_packet() -> OpenRgbResult<()> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+        let addr = listener.local_addr()?;
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut bytes = raw_packet(PacketId::DeviceListUpdated, &[1, 2, 3, 4, 5]);
+            bytes.extend(raw_packet(
+                PacketId::RequestControllerCount,
+                &3u32.to_le_bytes(),
+            ));
+            stream.write_all(&bytes).await.unwrap();
+            stream
+        });
+
+        let mut stream = ProtocolStream::connect(addr, 5).await?;
+        let err = stream
+            .read_packet::<u32>(0, PacketId::RequestControllerCount)
+            .await
+            .unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("DeviceListUpdated (100)"), "{msg}");
+        assert!(msg.contains("size 5"), "{msg}");
+
+        let count = stream
+            .read_packet::<u32>(0, PacketId::RequestControllerCount)
+            .await?;
+        assert_eq!(count, 3);
+        drop(server.await);
+        Ok(())
+    }
+
+    /// Serializes the payload of a packet with `packet_id` and `contents`.
+    fn payload<T: SerToBuf>(packet_id: PacketId, contents: T) -> Vec<u8> {
+        let mut buf = WriteMessage::new(5);
+        buf.write_value(&OpenRgbPacket::new(packet_id, contents))
+            .unwrap();
+        buf.bytes().to_vec()
+    }
+
+    #[test]
+    fn test_data_size_prefix() {
+        let colors = [crate::Color::new(1, 2, 3), crate::Color::new(4, 5, 6)];
+        // layouts of the OpenRGB SDK documentation
+        assert_eq!(
+            payload(PacketId::RGBControllerUpdateLeds, &colors[..]),
+            [14, 0, 0, 0, 2, 0, 1, 2, 3, 0, 4, 5, 6, 0]
+        );
+        assert_eq!(
+            payload(PacketId::RGBControllerUpdateZoneLeds, (1u32, &colors[..1])),
+            [14, 0, 0, 0, 1, 0, 0, 0, 1, 0, 1, 2, 3, 0]
+        );
+        assert_eq!(
+            payload(PacketId::RGBControllerUpdateSingleLed, (3i32, &colors[1])),
+            [3, 0, 0, 0, 4, 5, 6, 0]
+        );
+
+        assert_eq!(
+            DataSizePrefix::of(PacketId::RGBControllerResizeZone),
+            DataSizePrefix::None
+        );
+    }
+
+    #[test]
+    fn test_data_size_prefix_captured() -> OpenRgbResult<()> {
+        let capture = crate::protocol::mock::thermaltake_riing();
+        let capture = capture.bytes();
+        let prefix = DataSizePrefix::of(PacketId::RequestControllerData);
+        let mut msg = ReceivedMessage::new(capture, 3);
+        prefix.read(&mut msg)?;
+        let controller = msg.read_value::<crate::data::ControllerData>()?;
+        assert_eq!(msg.remaining(), 0);
+
+        let mut truncated = ReceivedMessage::new(&capture[..capture.len() - 1], 3);
+        assert!(prefix.read(&mut truncated).is_err());
+
+        // the payload of a mode update is the data size, the mode index and the mode as the server reports it
+        let mode = &controller.modes[1];
+        let mut mode_bytes = WriteMessage::new(3);
+        mode_bytes.write_value(mode)?;
+        let mode_bytes = mode_bytes.bytes();
+        assert!(capture.windows(mode_bytes.len()).any(|w| w == mode_bytes));
+        for packet_id in [
+            PacketId::RGBControllerUpdateMode,
+            PacketId::RGBControllerSaveMode,
+        ] {
+            let mut buf = WriteMessage::new(3);
+            buf.write_value(&OpenRgbPacket::new(packet_id, (1u32, mode)))?;
+            let bytes = buf.bytes();
+            assert_eq!(bytes[..4], (bytes.len() as u32).to_le_bytes());
+            assert_eq!(bytes[4..8], 1u32.to_le_bytes());
+            assert_eq!(&bytes[8..], mode_bytes);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_write_buffer_reused() -> OpenRgbResult<()> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+        let addr = listener.local_addr()?;
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut bytes = Vec::new();
+            stream.read_to_end(&mut bytes).await.unwrap();
+            bytes
+        });
+
+        let mut stream = ProtocolStream::connect(addr, 5).await?;
+        let colors = vec![crate::Color::new(1, 2, 3); 64];
+        let packet = OpenRgbPacket::new(PacketId::RGBControllerUpdateLeds, &colors);
+        stream
+            .write_packet(0, PacketId::RGBControllerUpdateLeds, &packet)
+            .await?;
+        let ptr = stream.write_buf.bytes().as_ptr();
+        for _ in 0..10 {
+            stream
+                .write_packet(0, PacketId::RGBControllerUpdateLeds, &packet)
+                .await?;
+        }
+        // no reallocation after the first packet
+        assert_eq!(stream.write_buf.bytes().as_ptr(), ptr);
+        stream.shutdown().await?;
+
+        let bytes = server.await.unwrap();
+        // header + length + color count + colors
+        let packet_len = 16 + 4 + 2 + 64 * 4;
+        assert_eq!(bytes.len(), 11 * packet_len);
+        let mut msg = ReceivedMessage::new(&bytes[..packet_len], 5);
+        assert_eq!(msg.read_value::<[u8; 4]>()?, OpenRgbMessageHeader::MAGIC);
+        assert_eq!(msg.read_u32()?, 0);
+        assert_eq!(
+            msg.read_u32()?,
+            u32::from(PacketId::RGBControllerUpdateLeds)
+        );
+        assert_eq!(msg.read_u32()?, packet_len as u32 - 16);
+        assert_eq!(msg.read_u32()?, packet_len as u32 - 16);
+        assert_eq!(msg.read_value::<Vec<crate::Color>>()?, colors);
+        Ok(())
+    }
+}